@@ -0,0 +1,248 @@
+use std::path::PathBuf;
+
+use opencv::core::Size;
+
+use crate::{
+    camera_calibration::{
+        CameraCalibration, CameraCalibrationTrait, ChessboardDetectorOptions, Detection, DetectorReport, ImageReport,
+    },
+    camera_model::CalibrationResult,
+    command_line::{CalibrationPattern, ChessboardDetector},
+    config::{CheckConfig, Config, CriteriaConfig, DetectionConfig, ModelConfig},
+    error::{CalibrationError, Result},
+    file::CustomFile,
+    target::TargetDescription,
+    view_check::ViewCheck,
+};
+
+/// 画像からカメラをキャリブレーションするビルダー
+///
+/// ```no_run
+/// use camera_calibrate::{CalibrationPattern, Calibrator};
+///
+/// let result = Calibrator::new(CalibrationPattern::ChessBoard)
+///     .target_file("./img/chessboard_dataset/target.json")
+///     .images("./img/chessboard_dataset")
+///     .calibrate()?;
+/// println!("fx = {}", result.intrinsics.fx);
+/// # Ok::<(), camera_calibrate::CalibrationError>(())
+/// ```
+///
+/// 設定は CLI の `Config` と同じで、指定しなかった値はパターンごとの既定値になる。
+/// CLI と異なり、プレビューは表示せず、出力ディレクトリにファイルも書き出さない
+#[derive(Debug, Clone)]
+pub struct Calibrator {
+    config: Config,
+    target: Option<TargetDescription>,
+    image_paths: Option<Vec<PathBuf>>,
+}
+
+impl Calibrator {
+    /// パターンごとの既定の設定で作成する
+    pub fn new(pattern: CalibrationPattern) -> Self {
+        let mut config = Config::default_for(pattern);
+        config.preview.enabled = false;
+        Self::from_config(config)
+    }
+
+    /// 設定ファイルなどから組み立てた設定で作成する
+    pub fn from_config(config: Config) -> Self {
+        Self {
+            config,
+            target: None,
+            image_paths: None,
+        }
+    }
+
+    /// ターゲット記述を直接与える (`target_file` より優先する)
+    pub fn target(mut self, target: TargetDescription) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// ターゲット記述ファイル
+    pub fn target_file(mut self, file_path: &str) -> Self {
+        self.config.dataset.target = file_path.to_string();
+        self
+    }
+
+    /// キャリブレーション画像のディレクトリまたはグロブパターン
+    pub fn images(mut self, source: &str) -> Self {
+        self.config.dataset.images = source.to_string();
+        self
+    }
+
+    /// キャリブレーション画像のパスを直接与える (`images` より優先する)
+    pub fn image_paths(mut self, image_paths: Vec<PathBuf>) -> Self {
+        self.image_paths = Some(image_paths);
+        self
+    }
+
+    /// サブディレクトリの画像も読み込む
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.config.dataset.recursive = recursive;
+        self
+    }
+
+    /// calibrate_camera に渡すモデルのフラグ
+    pub fn model(mut self, model: ModelConfig) -> Self {
+        self.config.calibration.model = model;
+        self
+    }
+
+    /// calibrate_camera の最適化の終了条件
+    pub fn criteria(mut self, criteria: CriteriaConfig) -> Self {
+        self.config.calibration.criteria = criteria;
+        self
+    }
+
+    /// corner_sub_pix によるコーナーの精緻化の終了条件
+    pub fn corner_criteria(mut self, criteria: CriteriaConfig) -> Self {
+        self.config.detection.corner_sub_pix.criteria = criteria;
+        self
+    }
+
+    /// 検出器の設定
+    pub fn detection(mut self, detection: DetectionConfig) -> Self {
+        self.config.detection = detection;
+        self
+    }
+
+    /// キャリブレーション前のビューのチェック
+    pub fn checks(mut self, checks: CheckConfig) -> Self {
+        self.config.checks = checks;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// 全画像からパターンを検出する
+    pub fn detect(&self) -> Result<(Vec<Detection>, Vec<ImageReport>)> {
+        self.config.validate()?;
+        let target = self.load_target()?;
+        let image_paths = match &self.image_paths {
+            Some(image_paths) => image_paths.clone(),
+            None => CustomFile::get_image_paths(
+                &self.config.dataset.images,
+                &self.config.dataset.extensions,
+                self.config.dataset.recursive,
+            )?,
+        };
+
+        let mut image_reports = Vec::new();
+        let detections = Self::detect_images(&self.config, &target, &image_paths, &mut image_reports, &mut Vec::new())?;
+        Ok((detections, image_reports))
+    }
+
+    /// パターンを検出し、ビューのチェックを行ってからキャリブレーションする
+    /// 検出結果と画像ごとの分類が必要な場合は `detect` を使う
+    pub fn calibrate(&self) -> Result<CalibrationResult> {
+        let (detections, _) = self.detect()?;
+        let (obj_points, img_points) = Detection::to_point_vectors(&detections);
+        let frame_size = Detection::image_size(&detections)?;
+
+        ViewCheck::run(&obj_points, &img_points, frame_size, &self.config.checks)?;
+
+        let output = CameraCalibration::calibrate_camera(
+            &obj_points,
+            &img_points,
+            frame_size,
+            self.config.calibration.model.to_flags(),
+            self.config.calibration.criteria.to_term_criteria()?,
+        )?;
+        let statistics = CameraCalibration::compute_reprojection_error(
+            &obj_points,
+            &img_points,
+            &output.rvecs,
+            &output.tvecs,
+            &output.camera_matrix,
+            &output.dist_coeffs,
+        )?;
+
+        let image_names: Vec<String> = detections.iter().map(|detection| detection.file_name.clone()).collect();
+        let units = self.load_target()?.units;
+        CalibrationResult::new(&output, &statistics, frame_size, &image_names, self.config.pattern, units)
+    }
+
+    fn load_target(&self) -> Result<TargetDescription> {
+        match &self.target {
+            Some(target) => Ok(target.clone()),
+            None => TargetDescription::load(&self.config.dataset.target),
+        }
+    }
+
+    /// 設定されたパターンを画像から検出する
+    ///
+    /// 検出できた画像の結果を返し、全画像の分類を `image_reports` に追加する。
+    /// チェスボードの場合は検出器ごとの結果を `detector_reports` に追加する
+    pub fn detect_images(
+        config: &Config,
+        target: &TargetDescription,
+        image_paths: &[PathBuf],
+        image_reports: &mut Vec<ImageReport>,
+        detector_reports: &mut Vec<DetectorReport>,
+    ) -> Result<Vec<Detection>> {
+        let criteria = config.detection.corner_sub_pix.criteria.to_term_criteria()?;
+
+        let mut read_image_cnt = 0;
+        match config.pattern {
+            CalibrationPattern::ChessBoard => {
+                target.expect_pattern(&[CalibrationPattern::ChessBoard])?;
+
+                let detection = &config.detection;
+                let mut detector_options = ChessboardDetectorOptions::new(
+                    detection.detector,
+                    detection.sb_exhaustive,
+                    detection.sb_accuracy,
+                    detection.sb_larger,
+                    detection.sb_marker,
+                );
+                if detection.partial {
+                    if detection.detector == ChessboardDetector::Classic {
+                        return Err(CalibrationError::Config(
+                            "partial board detection requires the sector based detector (detector = sb or both)".to_string(),
+                        ));
+                    }
+                    let (min_cols, min_rows) = detection.partial_min_size;
+                    detector_options = detector_options.with_partial_board(Size::new(min_cols, min_rows));
+                }
+
+                CameraCalibration::detect_chessboard_corners(
+                    image_paths,
+                    target,
+                    criteria,
+                    &mut read_image_cnt,
+                    image_reports,
+                    detection.corner_sub_pix.window_width,
+                    detection.corner_sub_pix.window_height,
+                    detection.corner_sub_pix.zero_zone,
+                    &detector_options,
+                    detector_reports,
+                    &config.preview,
+                )
+            }
+            CalibrationPattern::SymmetricCircleGrid | CalibrationPattern::AsymmetricCircleGrid => {
+                // 非対称グリッドのフラグと物体座標はターゲット記述のパターンから決まる
+                target.expect_pattern(&[config.pattern])?;
+                CameraCalibration::detect_circle_grid(image_paths, target, &mut read_image_cnt, image_reports, &config.preview)
+            }
+            CalibrationPattern::AprilGrid => {
+                target.expect_pattern(&[CalibrationPattern::AprilGrid])?;
+                CameraCalibration::detect_aprilgrid(
+                    image_paths,
+                    target,
+                    config.detection.min_tag_count,
+                    &mut read_image_cnt,
+                    image_reports,
+                    &config.preview,
+                )
+            }
+            CalibrationPattern::ChArUco => Err(CalibrationError::Config(format!(
+                "detection of the {:?} pattern is not implemented yet",
+                config.pattern
+            ))),
+        }
+    }
+}
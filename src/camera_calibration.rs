@@ -1,206 +1,496 @@
-use std::fs::File;
-use std::io::Write;
-
 use serde::{Serialize, Deserialize};
-use serde_json;
 use opencv::{
     calib3d, core::{self, Mat, Point2f, Point3f, Size, Vector},
     features2d::{self, SimpleBlobDetector, SimpleBlobDetector_Params},
     highgui,
     imgcodecs,
     imgproc,
+    objdetect,
     prelude::*,
 };
 use rayon::prelude::*;
 
 use crate::{
-    file::CustomFile,
-    GUI_WINDOW_HEIGHT,
-    GUI_WINDOW_WIDTH,
-    TEXT_COLOR,
-    TEXT_FONT_SCALE,
-    TEXT_POINT,
-    WAIT_KEY_DELAY,
-    WINDOW_TITLE
+    command_line::{CalibrationPattern, ChessboardDetector},
+    error::{CalibrationError, Result},
+    evaluation::ReprojectionStatistics,
+    config::PreviewConfig,
+    target::TargetDescription,
 };
 
+/// 旧形式 (schema_version の無い calibration.json) のキャリブレーション結果
+///
+/// 新しい形式は `CalibrationResult`。旧形式は読み込みのみ対応し、`CalibrationResult::load` で変換する
 #[derive(Serialize, Deserialize)]
 pub struct CameraCalibration {
-    camera_matrix: Vec<Vec<f64>>,
-    distortion_parameters: Vec<f64>,
-    rotation_vectors: Vec<Vec<f64>>,
-    translation_vectors: Vec<Vec<f64>>,
-    total_error: f64,
+    pub camera_matrix: Vec<Vec<f64>>,
+    pub distortion_parameters: Vec<f64>,
+    pub rotation_vectors: Vec<Vec<f64>>,
+    pub translation_vectors: Vec<Vec<f64>>,
+    /// ビューごとの誤差の平均
+    pub total_error: f64,
+}
+
+impl CameraCalibration {
+    /// カメラ行列を Mat (3x3, CV_64F) で返す
+    pub fn camera_matrix(&self) -> Result<Mat> {
+        Ok(Mat::from_slice_2d(&self.camera_matrix)?)
+    }
+
+    /// 歪み係数を Mat (1xN, CV_64F) で返す
+    pub fn dist_coeffs(&self) -> Result<Mat> {
+        Ok(Mat::from_slice(&self.distortion_parameters)?.try_clone()?)
+    }
+}
+
+/// calibrate_camera の結果
+pub struct CalibrationOutput {
+    pub camera_matrix: Mat,
+    pub dist_coeffs: Mat,
+    pub rvecs: Vector<Mat>,
+    pub tvecs: Vector<Mat>,
+    /// calibrate_camera が返す全点の RMS 再投影誤差
+    pub rms: f64,
+    /// 内部パラメータの標準偏差 (fx, fy, cx, cy, k1, k2, p1, p2, k3, k4, k5, k6, s1, s2, s3, s4, τx, τy)
+    pub std_intrinsics: Vec<f64>,
+    /// ビューごとの RMS 再投影誤差
+    pub per_view_errors: Vec<f64>,
+}
+
+/// チェスボード検出器の設定
+#[derive(Debug, Clone, Copy)]
+pub struct ChessboardDetectorOptions {
+    pub detector: ChessboardDetector,
+    /// find_chessboard_corners_sb に渡すフラグ
+    pub sb_flags: i32,
+    /// 部分的に見えているボードを検出する場合の最小サイズ (セクターベース検出器のみ)
+    pub partial_min_size: Option<Size>,
+}
+
+impl ChessboardDetectorOptions {
+    /// セクターベース検出器のフラグを組み立てる
+    pub fn new(detector: ChessboardDetector, exhaustive: bool, accuracy: bool, larger: bool, marker: bool) -> Self {
+        let mut sb_flags = calib3d::CALIB_CB_NORMALIZE_IMAGE;
+        if exhaustive {
+            sb_flags |= calib3d::CALIB_CB_EXHAUSTIVE;
+        }
+        if accuracy {
+            sb_flags |= calib3d::CALIB_CB_ACCURACY;
+        }
+        if larger {
+            sb_flags |= calib3d::CALIB_CB_LARGER;
+        }
+        if marker {
+            sb_flags |= calib3d::CALIB_CB_MARKER;
+        }
+
+        Self { detector, sb_flags, partial_min_size: None }
+    }
+
+    /// マーカー付きボードの部分検出を有効にする
+    pub fn with_partial_board(mut self, min_size: Size) -> Self {
+        self.sb_flags |= calib3d::CALIB_CB_LARGER | calib3d::CALIB_CB_MARKER;
+        self.partial_min_size = Some(min_size);
+        self
+    }
+}
+
+/// 1枚の画像の検出結果
+/// 検出キャッシュに保存できるように点列は OpenCV の型ではなく配列で持つ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Detection {
+    pub image_path: String,
+    pub file_name: String,
+    /// 画像サイズ (width, height)
+    pub image_size: (i32, i32),
+    /// 検出に使用した検出器
+    pub detector: String,
+    pub object_points: Vec<[f32; 3]>,
+    pub image_points: Vec<[f32; 2]>,
+}
+
+impl Detection {
+    pub fn new(
+        image_path: &std::path::Path,
+        image_size: Size,
+        detector: &str,
+        object_points: &Vector<Point3f>,
+        image_points: &Vector<Point2f>,
+    ) -> Self {
+        Self {
+            image_path: image_path.to_string_lossy().into_owned(),
+            file_name: image_path
+                .file_name()
+                .map(|f| f.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            image_size: (image_size.width, image_size.height),
+            detector: detector.to_string(),
+            object_points: object_points.iter().map(|p| [p.x, p.y, p.z]).collect(),
+            image_points: image_points.iter().map(|p| [p.x, p.y]).collect(),
+        }
+    }
+
+    pub fn object_points(&self) -> Vector<Point3f> {
+        self.object_points.iter().map(|p| Point3f::new(p[0], p[1], p[2])).collect()
+    }
+
+    pub fn image_points(&self) -> Vector<Point2f> {
+        self.image_points.iter().map(|p| Point2f::new(p[0], p[1])).collect()
+    }
+
+    /// 検出した画像に共通の画像サイズ
+    /// キャリブレーションは1つの画像サイズに対して行うため、サイズの異なる画像が混ざっている場合はエラーにする。
+    /// 検出できた画像が無い場合は calibrate_camera と同じくキャリブレーションのエラーとする
+    pub fn image_size(detections: &[Detection]) -> Result<Size> {
+        let Some(first) = detections.first() else {
+            return Err(CalibrationError::Calibration(
+                "the pattern was not detected in any image. Check the dataset and the target description".to_string(),
+            ));
+        };
+        if let Some(other) = detections.iter().find(|detection| detection.image_size != first.image_size) {
+            return Err(CalibrationError::Config(format!(
+                "all images must have the same size, but '{}' is {}x{} and '{}' is {}x{}",
+                first.image_path, first.image_size.0, first.image_size.1, other.image_path, other.image_size.0, other.image_size.1
+            )));
+        }
+        Ok(Size::new(first.image_size.0, first.image_size.1))
+    }
+
+    /// calibrate_camera などに渡すビューごとの点列に変換する
+    pub fn to_point_vectors(detections: &[Detection]) -> (Vector<Vector<Point3f>>, Vector<Vector<Point2f>>) {
+        let obj_points = detections.iter().map(Detection::object_points).collect();
+        let img_points = detections.iter().map(Detection::image_points).collect();
+        (obj_points, img_points)
+    }
+}
+
+/// 入力画像の分類
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageStatus {
+    /// 画像を読み込めなかった (壊れている、または対応していない形式)
+    Undecodable,
+    /// 読み込めたがパターンが見つからなかった
+    PatternNotFound,
+    /// パターンを検出し、キャリブレーションに使用する
+    Detected,
+    /// パターンは見つかったが、キャリブレーションに使えないため除外した
+    /// (部分検出で原点マーカーが見えない、AprilGrid のタグが足りない など)
+    Rejected,
+}
+
+/// 入力画像ごとの分類結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageReport {
+    pub image_path: String,
+    pub status: ImageStatus,
+    /// 読み込めなかった理由や除外した理由
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ImageReport {
+    pub fn new(image_path: &std::path::Path, status: ImageStatus, detail: Option<String>) -> Self {
+        Self {
+            image_path: image_path.to_string_lossy().into_owned(),
+            status,
+            detail,
+        }
+    }
+}
+
+/// 入力画像の分類の集計 (detection_report.json)
+#[derive(Serialize)]
+pub struct DetectionReport<'a> {
+    pub total: usize,
+    pub undecodable: usize,
+    pub pattern_not_found: usize,
+    pub detected: usize,
+    pub rejected: usize,
+    pub images: Vec<&'a ImageReport>,
+}
+
+impl<'a> DetectionReport<'a> {
+    pub fn new(images: Vec<&'a ImageReport>) -> Self {
+        let count = |status: ImageStatus| images.iter().filter(|report| report.status == status).count();
+        Self {
+            total: images.len(),
+            undecodable: count(ImageStatus::Undecodable),
+            pattern_not_found: count(ImageStatus::PatternNotFound),
+            detected: count(ImageStatus::Detected),
+            rejected: count(ImageStatus::Rejected),
+            images,
+        }
+    }
+
+    /// 分類ごとの枚数を表示する
+    pub fn print_summary(&self) {
+        println!(
+            "Images: {} (detected: {}, pattern not found: {}, undecodable: {}, rejected: {})",
+            self.total, self.detected, self.pattern_not_found, self.undecodable, self.rejected
+        );
+    }
+}
+
+/// 1枚の画像から検出されたコーナー
+struct DetectedCorners {
+    pattern_size: Size,
+    object_points: Vector<Point3f>,
+    image_points: Vector<Point2f>,
+}
+
+/// 部分検出の結果
+enum PartialBoard {
+    Found(DetectedCorners),
+    NotFound,
+    /// ボードは見つかったが使えない (理由)
+    Rejected(String),
+}
+
+/// 画像ごとのチェスボード検出器の結果 (detector_report.json)
+/// 検出キャッシュにも保存し、キャッシュを使った再実行でも全画像の結果を書き出せるようにする
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetectorReport {
+    pub image_path: String,
+    pub file_name: String,
+    /// 検出器を実行しなかった場合は None
+    pub classic: Option<bool>,
+    pub sector_based: Option<bool>,
+    /// 結果を採用した検出器
+    pub used: Option<String>,
+    pub corner_count: usize,
+}
+
+impl DetectorReport {
+    /// 検出器ごとの成功数を表示する
+    pub fn print_summary(reports: &[DetectorReport]) {
+        let classic_cnt = reports.iter().filter(|r| r.classic == Some(true)).count();
+        let sector_based_cnt = reports.iter().filter(|r| r.sector_based == Some(true)).count();
+        println!("Classic detector: {} / {} images", classic_cnt, reports.len());
+        println!("Sector based detector: {} / {} images", sector_based_cnt, reports.len());
+    }
 }
 
 pub trait CameraCalibrationTrait {
     /// チェスボードのコーナー検出 & 精緻化
+    /// 画像ごとにどの検出器で検出できたかを `detector_reports` に追加する
+    #[allow(clippy::too_many_arguments)]
     fn detect_chessboard_corners(
         image_paths: &[std::path::PathBuf],
-        chessboard_size: Size,
+        target: &TargetDescription,
         criteria: core::TermCriteria,
         read_image_cnt: &mut i32,
-        failed_read_image_path: &str,
+        image_reports: &mut Vec<ImageReport>,
         corner_sub_pix_window_width: i32,
         corner_sub_pix_window_height: i32,
         corner_sub_pix_zero_zone: i32,
-    ) -> opencv::Result<(Vector<Vector<Point3f>>, Vector<Vector<Point2f>>)>;
+        detector_options: &ChessboardDetectorOptions,
+        detector_reports: &mut Vec<DetectorReport>,
+        preview: &PreviewConfig,
+    ) -> Result<Vec<Detection>>;
 
     /// 円グリッドのコーナー検出 & 精緻化
     fn detect_circle_grid(
         image_paths: &[std::path::PathBuf],
-        pattern_size: Size,
+        target: &TargetDescription,
+        read_image_cnt: &mut i32,
+        image_reports: &mut Vec<ImageReport>,
+        preview: &PreviewConfig,
+    ) -> Result<Vec<Detection>>;
+
+    /// AprilGrid (Kalibr形式) のタグ検出
+    /// タグIDごとに4隅の物体座標を決めるため、一部が隠れていても見えているタグをすべて利用できる
+    fn detect_aprilgrid(
+        image_paths: &[std::path::PathBuf],
+        target: &TargetDescription,
+        min_tag_count: usize,
         read_image_cnt: &mut i32,
-        failed_read_image_path: &str
-    ) -> opencv::Result<(Vector<Vector<Point3f>>, Vector<Vector<Point2f>>)>;
+        image_reports: &mut Vec<ImageReport>,
+        preview: &PreviewConfig,
+    ) -> Result<Vec<Detection>>;
 
     /// カメラキャリブレーション
     fn calibrate_camera(
         obj_points: &Vector<Vector<Point3f>>,
         img_points: &Vector<Vector<Point2f>>,
         frame_size: Size,
+        flags: i32,
         criteria: core::TermCriteria,
-    ) -> opencv::Result<(Mat, Mat, Vector<Mat>, Vector<Mat>)>;
+    ) -> Result<CalibrationOutput>;
 
     /// 画像の歪み補正
-    fn undistort_image(camera_matrix: &Mat, dist_coeffs: &Mat, undistort_image_path: &str, result_image_path: &str, output_directory_name: &str) -> opencv::Result<()>;
+    fn undistort_image(camera_matrix: &Mat, dist_coeffs: &Mat, undistort_image_path: &str, result_image_path: &str) -> Result<()>;
 
-    /// 再投影誤差を計算
-    fn compute_reprojection_error(
+    /// 固定した内部パラメータで各ビューのポーズ (rvec, tvec) を求める
+    /// 解が求まらなかったビューは None
+    fn solve_poses(
         obj_points: &Vector<Vector<Point3f>>,
         img_points: &Vector<Vector<Point2f>>,
-        rvecs: &Vector<Mat>,
-        tvecs: &Vector<Mat>,
         camera_matrix: &Mat,
         dist_coeffs: &Mat,
-    ) -> opencv::Result<f64>;
+    ) -> Result<Vec<Option<(Mat, Mat)>>>;
 
-    /// カメラキャリブレーション結果をJSON形式で保存
-    fn save_to_json(
+    /// ビューごとの各点の再投影誤差 (実測点と投影点の距離 [px])
+    /// 投影に失敗したビューは None
+    fn reprojection_residuals(
+        obj_points: &Vector<Vector<Point3f>>,
+        img_points: &Vector<Vector<Point2f>>,
+        rvecs: &Vector<Mat>,
+        tvecs: &Vector<Mat>,
         camera_matrix: &Mat,
         dist_coeffs: &Mat,
+    ) -> Result<Vec<Option<Vec<f64>>>>;
+
+    /// 再投影誤差の統計を計算
+    fn compute_reprojection_error(
+        obj_points: &Vector<Vector<Point3f>>,
+        img_points: &Vector<Vector<Point2f>>,
         rvecs: &Vector<Mat>,
         tvecs: &Vector<Mat>,
-        error: f64,
-        filename: &str
-    ) -> std::io::Result<()>;
+        camera_matrix: &Mat,
+        dist_coeffs: &Mat,
+    ) -> Result<ReprojectionStatistics>;
 }
 
 impl CameraCalibrationTrait for CameraCalibration {
     fn detect_chessboard_corners(
         image_paths: &[std::path::PathBuf],
-        chessboard_size: Size,
+        target: &TargetDescription,
         criteria: core::TermCriteria,
         read_image_cnt: &mut i32,
-        failed_read_image_path: &str,
+        image_reports: &mut Vec<ImageReport>,
         corner_sub_pix_window_width: i32,
         corner_sub_pix_window_height: i32,
         corner_sub_pix_zero_zone: i32,
-    ) -> opencv::Result<(Vector<Vector<Point3f>>, Vector<Vector<Point2f>>)> {
-        let mut objp = Vector::<Point3f>::new();
-        for i in 0..chessboard_size.height {
-            for j in 0..chessboard_size.width {
-                objp.push(Point3f::new(j as f32, i as f32, 0.0));
-            }
-        }
+        detector_options: &ChessboardDetectorOptions,
+        detector_reports: &mut Vec<DetectorReport>,
+        preview: &PreviewConfig,
+    ) -> Result<Vec<Detection>> {
+        let chessboard_size = target.pattern_size();
+        let objp = target.object_points();
     
-        let mut obj_points = Vector::<Vector<Point3f>>::new();
-        let mut img_points = Vector::<Vector<Point2f>>::new();
+        let mut detections = Vec::new();
     
-        highgui::named_window(WINDOW_TITLE, highgui::WINDOW_NORMAL)?;
-        highgui::resize_window(WINDOW_TITLE, GUI_WINDOW_WIDTH, GUI_WINDOW_HEIGHT)?;
+        open_preview(preview)?;
 
-        let mut failed_images = Vec::new();
-    
         for image_path in image_paths {
-            let img = imgcodecs::imread(image_path.to_str().unwrap(), imgcodecs::IMREAD_COLOR)?;
+            let file_name = image_path
+                .file_name()
+                .and_then(|f| f.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let Some(img) = read_image_or_report(image_path, image_reports)? else {
+                continue;
+            };
             let mut gray = Mat::default();
-            imgproc::cvt_color(&img, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
-    
-            let mut corners = Vector::<Point2f>::new();
-            let found = calib3d::find_chessboard_corners(
-                &gray,
-                chessboard_size,
-                &mut corners,
-                calib3d::CALIB_CB_ADAPTIVE_THRESH
-                    | calib3d::CALIB_CB_FAST_CHECK
-                    | calib3d::CALIB_CB_NORMALIZE_IMAGE,
-            )?;
-    
-            if found {
-                obj_points.push(objp.clone());
-    
-                let mut refined_corners = corners.clone();
-                imgproc::corner_sub_pix(
+            imgproc::cvt_color_def(&img, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+
+            // 選択された検出器でコーナーを検出する
+            let mut rejected = None;
+            let sector_based = match detector_options.detector {
+                ChessboardDetector::SectorBased | ChessboardDetector::Both => match detector_options.partial_min_size {
+                    Some(min_size) => Some(
+                        match find_corners_partial(&gray, chessboard_size, target.spacing as f32, min_size, detector_options.sb_flags)? {
+                            PartialBoard::Found(corners) => Some(corners),
+                            PartialBoard::NotFound => None,
+                            PartialBoard::Rejected(reason) => {
+                                rejected = Some(reason);
+                                None
+                            }
+                        },
+                    ),
+                    None => Some(find_corners_sector_based(&gray, chessboard_size, &objp, detector_options.sb_flags)?),
+                },
+                ChessboardDetector::Classic => None,
+            };
+            let classic = match detector_options.detector {
+                ChessboardDetector::Classic | ChessboardDetector::Both => Some(find_corners_classic(
                     &gray,
-                    &mut refined_corners,
-                    Size::new(corner_sub_pix_window_width, corner_sub_pix_window_height),
-                    Size::new(corner_sub_pix_zero_zone, corner_sub_pix_zero_zone),
+                    chessboard_size,
+                    &objp,
                     criteria,
-                )?;
-                img_points.push(refined_corners);
-    
-                let mut img_clone = img.clone();
-                calib3d::draw_chessboard_corners(&mut img_clone, chessboard_size, &corners, found)?;
-    
-                // Read each file and display the filename on the window
-                if let Some(filename) = image_path.file_name().and_then(|f| f.to_str()) {
-                    *read_image_cnt += 1;
-                    let text = format!("{}", filename);
-                    let org = core::Point::new(TEXT_POINT.0, TEXT_POINT.1);
-                    let font_face = imgproc::FONT_HERSHEY_SIMPLEX;
-                    let font_scale = TEXT_FONT_SCALE;
-                    let color = core::Scalar::new(TEXT_COLOR.0, TEXT_COLOR.1, TEXT_COLOR.2, TEXT_COLOR.3);
-                    let thickness = 2;
-                    imgproc::put_text(&mut img_clone, &text, org, font_face, font_scale, color, thickness, imgproc::LINE_AA, false)?;
+                    corner_sub_pix_window_width,
+                    corner_sub_pix_window_height,
+                    corner_sub_pix_zero_zone,
+                )?),
+                ChessboardDetector::SectorBased => None,
+            };
+
+            let classic_found = classic.as_ref().map(Option::is_some);
+            let sector_based_found = sector_based.as_ref().map(Option::is_some);
+
+            // 両方成功した場合はセクターベースの結果を優先する
+            let detected = match (sector_based, classic) {
+                (Some(Some(corners)), _) => Some(("sector_based", corners)),
+                (_, Some(Some(corners))) => Some(("classic", corners)),
+                _ => None,
+            };
+
+            detector_reports.push(DetectorReport {
+                image_path: image_path.to_string_lossy().into_owned(),
+                file_name: file_name.clone(),
+                classic: classic_found,
+                sector_based: sector_based_found,
+                used: detected.as_ref().map(|(used, _)| used.to_string()),
+                corner_count: detected.as_ref().map(|(_, corners)| corners.image_points.len()).unwrap_or(0),
+            });
+
+            if let Some((used, corners)) = detected {
+                println!("{}: detected {} corners by {} detector", file_name, corners.image_points.len(), used);
+                detections.push(Detection::new(image_path, img.size()?, used, &corners.object_points, &corners.image_points));
+                image_reports.push(ImageReport::new(image_path, ImageStatus::Detected, None));
+                *read_image_cnt += 1;
+
+                if preview.enabled {
+                    let mut img_clone = img.clone();
+                    calib3d::draw_chessboard_corners(&mut img_clone, corners.pattern_size, &corners.image_points, true)?;
+                    show_preview(preview, &mut img_clone, &file_name)?;
                 }
-    
-                highgui::imshow(WINDOW_TITLE, &img_clone)?;
-                highgui::wait_key(WAIT_KEY_DELAY)?;
+            } else if let Some(reason) = rejected {
+                println!("{}: rejected the partial board: {}", file_name, reason);
+                image_reports.push(ImageReport::new(image_path, ImageStatus::Rejected, Some(reason)));
             } else {
-                if let Some(filename) = image_path.file_name().and_then(|f| f.to_str()) {
-                    failed_images.push(filename.to_string());
-                }
+                image_reports.push(ImageReport::new(image_path, ImageStatus::PatternNotFound, None));
             }
         }
     
         println!("Detected {} images", read_image_cnt);
-        highgui::destroy_all_windows()?;
-
-        if !failed_images.is_empty() {
-            let _ = CustomFile::create_output_json(failed_read_image_path, failed_images);
-        }
+        close_preview(preview)?;
 
-        Ok((obj_points, img_points))
+        Ok(detections)
     }
 
     fn detect_circle_grid(
         image_paths: &[std::path::PathBuf],
-        pattern_size: Size,
+        target: &TargetDescription,
         read_image_cnt: &mut i32,
-        failed_read_image_path: &str
-    ) -> opencv::Result<(Vector<Vector<Point3f>>, Vector<Vector<Point2f>>)> {
-        let mut objp = Vector::<Point3f>::new();
-        for i in 0..pattern_size.height {
-            for j in 0..pattern_size.width {
-                objp.push(Point3f::new(j as f32, i as f32, 0.0));
-            }
-        }
-
-        let mut obj_points = Vector::<Vector<Point3f>>::new();
-        let mut img_points = Vector::<Vector<Point2f>>::new();
+        image_reports: &mut Vec<ImageReport>,
+        preview: &PreviewConfig,
+    ) -> Result<Vec<Detection>> {
+        let pattern_size = target.pattern_size();
+        let objp = target.object_points();
+        let grid_flags = match target.pattern {
+            CalibrationPattern::AsymmetricCircleGrid => calib3d::CALIB_CB_ASYMMETRIC_GRID,
+            _ => calib3d::CALIB_CB_SYMMETRIC_GRID,
+        };
 
-        let mut failed_images = Vec::new();
+        let mut detections = Vec::new();
 
-        highgui::named_window(WINDOW_TITLE, highgui::WINDOW_NORMAL)?;
-        highgui::resize_window(WINDOW_TITLE, GUI_WINDOW_WIDTH, GUI_WINDOW_HEIGHT)?;
+        open_preview(preview)?;
 
         for image_path in image_paths {
-            let img = imgcodecs::imread(image_path.to_str().unwrap(), imgcodecs::IMREAD_COLOR)?;
+            let file_name = image_path
+                .file_name()
+                .and_then(|f| f.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let Some(img) = read_image_or_report(image_path, image_reports)? else {
+                continue;
+            };
             let mut gray = Mat::default();
-            let gray_clone = gray.clone();
-            imgproc::cvt_color(&img, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
-            imgproc::equalize_hist(&gray_clone, &mut gray)?;
+            imgproc::cvt_color_def(&img, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+            let mut equalized = Mat::default();
+            imgproc::equalize_hist(&gray, &mut equalized)?;
 
             let mut centers = Vector::<Point2f>::new();
 
@@ -211,65 +501,140 @@ impl CameraCalibrationTrait for CameraCalibration {
             let grid_params = calib3d::CirclesGridFinderParameters::default()?;
 
             let found = calib3d::find_circles_grid(
-                &gray,
+                &equalized,
                 pattern_size,
                 &mut centers,
-                calib3d::CALIB_CB_SYMMETRIC_GRID,
+                grid_flags,
                 Some(&blob_detector_ptr),
                 grid_params,
             )?;
 
             if found {
-                obj_points.push(objp.clone());
-                img_points.push(centers.clone());
-
-                let mut img_clone = img.clone();
-                calib3d::draw_chessboard_corners(&mut img_clone, pattern_size, &centers, found)?;
-
-                // Read each file and display the filename on the window
-                if let Some(filename) = image_path.file_name().and_then(|f| f.to_str()) {
-                    *read_image_cnt += 1;
-                    let text = format!("{}", filename);
-                    let org = core::Point::new(TEXT_POINT.0, TEXT_POINT.1);
-                    let font_face = imgproc::FONT_HERSHEY_SIMPLEX;
-                    let font_scale = TEXT_FONT_SCALE;
-                    let color = core::Scalar::new(TEXT_COLOR.0, TEXT_COLOR.1, TEXT_COLOR.2, TEXT_COLOR.3);
-                    let thickness = 2;
-                    imgproc::put_text(&mut img_clone, &text, org, font_face, font_scale, color, thickness, imgproc::LINE_AA, false)?;
+                detections.push(Detection::new(image_path, img.size()?, "circle_grid", &objp, &centers));
+                image_reports.push(ImageReport::new(image_path, ImageStatus::Detected, None));
+                *read_image_cnt += 1;
+
+                if preview.enabled {
+                    let mut img_clone = img.clone();
+                    calib3d::draw_chessboard_corners(&mut img_clone, pattern_size, &centers, found)?;
+                    show_preview(preview, &mut img_clone, &file_name)?;
                 }
-
-                highgui::imshow(WINDOW_TITLE, &img_clone)?;
-                highgui::wait_key(WAIT_KEY_DELAY)?;
             } else {
-                if let Some(filename) = image_path.file_name().and_then(|f| f.to_str()) {
-                    failed_images.push(filename.to_string());
-                }
-            }      
+                image_reports.push(ImageReport::new(image_path, ImageStatus::PatternNotFound, None));
+            }
         }
 
         println!("Detected {} images", read_image_cnt);
-        highgui::destroy_all_windows()?;
+        close_preview(preview)?;
+
+        Ok(detections)
+    }
+
+    fn detect_aprilgrid(
+        image_paths: &[std::path::PathBuf],
+        target: &TargetDescription,
+        min_tag_count: usize,
+        read_image_cnt: &mut i32,
+        image_reports: &mut Vec<ImageReport>,
+        preview: &PreviewConfig,
+    ) -> Result<Vec<Detection>> {
+        // Kalibr の AprilGrid は tag36h11 を使用する
+        let dictionary = target.marker_dictionary("DICT_APRILTAG_36h11")?;
+        let mut detector_params = objdetect::DetectorParameters::default()?;
+        detector_params.set_corner_refinement_method(objdetect::CORNER_REFINE_SUBPIX);
+        let detector = objdetect::ArucoDetector::new(&dictionary, &detector_params, objdetect::RefineParameters::new_def()?)?;
 
-        if !failed_images.is_empty() {
-            let _ = CustomFile::create_output_json(failed_read_image_path, failed_images);
+        let mut detections = Vec::new();
+
+        open_preview(preview)?;
+
+        for image_path in image_paths {
+            let file_name = image_path
+                .file_name()
+                .and_then(|f| f.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let Some(img) = read_image_or_report(image_path, image_reports)? else {
+                continue;
+            };
+            let mut gray = Mat::default();
+            imgproc::cvt_color_def(&img, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+
+            let mut tag_corners = Vector::<Vector<Point2f>>::new();
+            let mut tag_ids = Vector::<i32>::new();
+            let mut rejected = Vector::<Vector<Point2f>>::new();
+            detector.detect_markers(&gray, &mut tag_corners, &mut tag_ids, &mut rejected)?;
+
+            // グリッドに属するタグのコーナーだけを対応付ける
+            let mut objp = Vector::<Point3f>::new();
+            let mut imgp = Vector::<Point2f>::new();
+            let mut used_tag_cnt = 0;
+            for (tag_id, corners) in tag_ids.iter().zip(tag_corners.iter()) {
+                let Some(tag_object_points) = target.aprilgrid_tag_corners(tag_id) else {
+                    continue;
+                };
+                if corners.len() != 4 {
+                    continue;
+                }
+                for (object_point, image_point) in tag_object_points.iter().zip(corners.iter()) {
+                    objp.push(*object_point);
+                    imgp.push(image_point);
+                }
+                used_tag_cnt += 1;
+            }
+
+            if used_tag_cnt >= min_tag_count {
+                println!("{}: detected {} tags", file_name, used_tag_cnt);
+                detections.push(Detection::new(image_path, img.size()?, "aprilgrid", &objp, &imgp));
+                image_reports.push(ImageReport::new(image_path, ImageStatus::Detected, None));
+                *read_image_cnt += 1;
+
+                if preview.enabled {
+                    let mut img_clone = img.clone();
+                    objdetect::draw_detected_markers(&mut img_clone, &tag_corners, &tag_ids, core::Scalar::new(0.0, 0.0, 255.0, 0.0))?;
+                    show_preview(preview, &mut img_clone, &file_name)?;
+                }
+            } else if used_tag_cnt > 0 {
+                // タグは見つかったが、キャリブレーションに使うには少なすぎる
+                println!("{}: rejected, only {} tags detected", file_name, used_tag_cnt);
+                image_reports.push(ImageReport::new(
+                    image_path,
+                    ImageStatus::Rejected,
+                    Some(format!("only {} tags detected (min_tag_count = {})", used_tag_cnt, min_tag_count)),
+                ));
+            } else {
+                image_reports.push(ImageReport::new(image_path, ImageStatus::PatternNotFound, None));
+            }
         }
 
-        println!("END");
-        Ok((obj_points, img_points))
+        println!("Detected {} images", read_image_cnt);
+        close_preview(preview)?;
+
+        Ok(detections)
     }
 
     fn calibrate_camera(
         obj_points: &Vector<Vector<Point3f>>,
         img_points: &Vector<Vector<Point2f>>,
         frame_size: Size,
+        flags: i32,
         criteria: core::TermCriteria,
-    ) -> opencv::Result<(Mat, Mat, Vector<Mat>, Vector<Mat>)> {
+    ) -> Result<CalibrationOutput> {
+        if obj_points.is_empty() {
+            return Err(CalibrationError::Calibration(
+                "the pattern was not detected in any image. Check the dataset and the target description".to_string(),
+            ));
+        }
+
         let mut camera_matrix = Mat::default();
         let mut dist_coeffs = Mat::default();
         let mut rvecs = Vector::<Mat>::new();
         let mut tvecs = Vector::<Mat>::new();
+        let mut std_intrinsics = Mat::default();
+        let mut std_extrinsics = Mat::default();
+        let mut per_view_errors = Mat::default();
 
-        let ret = calib3d::calibrate_camera(
+        let ret = calib3d::calibrate_camera_extended(
             obj_points,
             img_points,
             frame_size,
@@ -277,140 +642,312 @@ impl CameraCalibrationTrait for CameraCalibration {
             &mut dist_coeffs,
             &mut rvecs,
             &mut tvecs,
-            0,
+            &mut std_intrinsics,
+            &mut std_extrinsics,
+            &mut per_view_errors,
+            flags,
             criteria,
         )?;
 
         // I leave this output because it may be useful for future cases where
         // the program needs to handle numerical values with extremely high precision such as microscopes.
-        println!("Camera Calibrated: {}", ret);
+        // calibrateCamera は反復回数を返さないため、最終的な RMS と一緒に終了条件を残す
+        println!(
+            "Camera Calibrated: {} (max_count = {}, eps = {}; OpenCV does not report the iteration count)",
+            ret, criteria.max_count, criteria.epsilon
+        );
         println!("Camera Matrix:\n{:?}", camera_matrix);
         println!("Distortion Parameters:\n{:?}", dist_coeffs);
 
-        Ok((camera_matrix, dist_coeffs, rvecs, tvecs))
-    }
-
-    fn undistort_image(camera_matrix: &Mat, dist_coeffs: &Mat, undistort_image_path: &str, result_image_path: &str, output_directory_name: &str) -> opencv::Result<()> {
-        let img = imgcodecs::imread(undistort_image_path, imgcodecs::IMREAD_COLOR)?;
-        let size = img.size()?;
-        let new_camera_matrix = Mat::default();
-        let mut roi = core::Rect::default();
-
-        calib3d::get_optimal_new_camera_matrix(
+        Ok(CalibrationOutput {
             camera_matrix,
             dist_coeffs,
-            size,
-            1.0,
-            size,
-            Some(&mut roi),
-            false,
-        )?;
+            rvecs,
+            tvecs,
+            rms: ret,
+            std_intrinsics: std_intrinsics.data_typed::<f64>()?.to_vec(),
+            per_view_errors: per_view_errors.data_typed::<f64>()?.to_vec(),
+        })
+    }
+
+    fn undistort_image(camera_matrix: &Mat, dist_coeffs: &Mat, undistort_image_path: &str, result_image_path: &str) -> Result<()> {
+        let img = read_image(std::path::Path::new(undistort_image_path))?;
 
+        // 新しいカメラ行列を与えないため、出力は元のカメラ行列で投影される (画像サイズはそのまま、周辺は切り取られる)
         let mut dst = Mat::default();
-        calib3d::undistort(&img, &mut dst, camera_matrix, dist_coeffs, &new_camera_matrix)?;
+        calib3d::undistort(&img, &mut dst, camera_matrix, dist_coeffs, &core::no_array())?;
 
-        CustomFile::create_out_dir(Some(output_directory_name));
         imgcodecs::imwrite(result_image_path, &dst, &Vector::new())?;
         Ok(())
     }
 
-    fn compute_reprojection_error(
+    fn solve_poses(
+        obj_points: &Vector<Vector<Point3f>>,
+        img_points: &Vector<Vector<Point2f>>,
+        camera_matrix: &Mat,
+        dist_coeffs: &Mat,
+    ) -> Result<Vec<Option<(Mat, Mat)>>> {
+        let mut poses = Vec::with_capacity(obj_points.len());
+
+        for (object_points, image_points) in obj_points.iter().zip(img_points.iter()) {
+            let mut rvec = Mat::default();
+            let mut tvec = Mat::default();
+            let solved = calib3d::solve_pnp(
+                &object_points,
+                &image_points,
+                camera_matrix,
+                dist_coeffs,
+                &mut rvec,
+                &mut tvec,
+                false,
+                calib3d::SOLVEPNP_ITERATIVE,
+            )?;
+            poses.push(solved.then_some((rvec, tvec)));
+        }
+
+        Ok(poses)
+    }
+
+    fn reprojection_residuals(
         obj_points: &Vector<Vector<Point3f>>,
         img_points: &Vector<Vector<Point2f>>,
         rvecs: &Vector<Mat>,
         tvecs: &Vector<Mat>,
         camera_matrix: &Mat,
         dist_coeffs: &Mat,
-    ) -> opencv::Result<f64> {
-        let errors: Vec<f64> = (0..obj_points.len())
+    ) -> Result<Vec<Option<Vec<f64>>>> {
+        let residuals = (0..obj_points.len())
             .into_par_iter()
-            .map(|i| {
+            .map(|i| -> opencv::Result<Option<Vec<f64>>> {
                 let mut img_points2 = Vector::<Point2f>::new();
                 let mut jacobian = Mat::default();
-    
-                if let Ok(_) = calib3d::project_points(
-                    &obj_points.get(i).unwrap(),
-                    &rvecs.get(i).unwrap(),
-                    &tvecs.get(i).unwrap(),
+
+                if calib3d::project_points(
+                    &obj_points.get(i)?,
+                    &rvecs.get(i)?,
+                    &tvecs.get(i)?,
                     camera_matrix,
                     dist_coeffs,
                     &mut img_points2,
                     &mut jacobian,
                     0.0,
-                ) {
-                    // 実測点と投影点の差分を計算
-                    let diff: Vec<Point2f> = img_points.get(i).unwrap()
-                        .iter()
-                        .zip(img_points2.iter())
-                        .map(|(p1, p2)| Point2f::new(p1.x - p2.x, p1.y - p2.y))
-                        .collect();
-    
-                    // 二乗平均平方根誤差を計算
-                    let squared_errors: f64 = diff.iter()
-                        .map(|p| (p.x * p.x + p.y * p.y) as f64)
-                        .sum();
-                    
-                    (squared_errors / diff.len() as f64).sqrt()
-                } else {
-                    0.0
+                )
+                .is_err()
+                {
+                    return Ok(None);
                 }
+
+                // 実測点と投影点の距離
+                let errors = img_points
+                    .get(i)?
+                    .iter()
+                    .zip(img_points2.iter())
+                    .map(|(p1, p2)| ((p1.x - p2.x) as f64).hypot((p1.y - p2.y) as f64))
+                    .collect();
+                Ok(Some(errors))
             })
-            .collect();
-    
-        let mean_error = errors.iter().sum::<f64>() / obj_points.len() as f64;
-        Ok(mean_error)
+            .collect::<opencv::Result<Vec<Option<Vec<f64>>>>>()?;
+
+        Ok(residuals)
     }
 
-    fn save_to_json(
-        camera_matrix: &Mat,
-        dist_coeffs: &Mat,
+    fn compute_reprojection_error(
+        obj_points: &Vector<Vector<Point3f>>,
+        img_points: &Vector<Vector<Point2f>>,
         rvecs: &Vector<Mat>,
         tvecs: &Vector<Mat>,
-        error: f64,
-        filename: &str,
-    ) -> std::io::Result<()> {
-        //  カメラ行列をVec<Vec<f64>>に変換
-        let rows = camera_matrix.rows() as usize;
-        let cols = camera_matrix.cols() as usize;
-        let mut camera_matrix_vec = vec![vec![0.0; cols]; rows];
-        for i in 0..rows {
-            for j in 0..cols {
-                camera_matrix_vec[i][j] = *camera_matrix.at_2d::<f64>(i as i32, j as i32).unwrap();
-            }
+        camera_matrix: &Mat,
+        dist_coeffs: &Mat,
+    ) -> Result<ReprojectionStatistics> {
+        let residuals = Self::reprojection_residuals(obj_points, img_points, rvecs, tvecs, camera_matrix, dist_coeffs)?;
+        Ok(ReprojectionStatistics::new(&residuals))
+    }
+}
+
+/// 画像を読み込む
+/// imread は壊れたファイルや対応していない形式に対して空の Mat を返すため、ここでエラーにする
+pub(crate) fn read_image(image_path: &std::path::Path) -> Result<Mat> {
+    let path = image_path.to_string_lossy();
+    if !image_path.exists() {
+        return Err(CalibrationError::io(
+            path,
+            std::io::Error::new(std::io::ErrorKind::NotFound, "image file not found"),
+        ));
+    }
+
+    let img = imgcodecs::imread(&path, imgcodecs::IMREAD_COLOR)?;
+    if img.empty() {
+        return Err(CalibrationError::ImageDecode { path: path.into_owned() });
+    }
+    Ok(img)
+}
+
+/// 画像を読み込む。デコードできない画像は Undecodable として記録し、None を返す
+fn read_image_or_report(image_path: &std::path::Path, image_reports: &mut Vec<ImageReport>) -> Result<Option<Mat>> {
+    match read_image(image_path) {
+        Ok(img) => Ok(Some(img)),
+        Err(e @ CalibrationError::ImageDecode { .. }) => {
+            eprintln!("{}", e);
+            image_reports.push(ImageReport::new(image_path, ImageStatus::Undecodable, Some(e.to_string())));
+            Ok(None)
         }
+        Err(e) => Err(e),
+    }
+}
 
-        let dist_coeffs_vec: Vec<f64> = (0..dist_coeffs.total())
-            .map(|i| *dist_coeffs.at::<f64>(i as i32).unwrap())
-            .collect();
-
-        // 回転ベクトルと並進ベクトルをVec<Vec<f64>>に変換
-        let mut rotation_vectors = Vec::new();
-        let mut translation_vectors = Vec::new();
-        for rvec in rvecs {
-            let rvec_vec = (0..rvec.total() as usize)
-                .map(|i| *rvec.at::<f64>(i as i32).unwrap())
-                .collect::<Vec<f64>>();
-            rotation_vectors.push(rvec_vec);
+/// find_chessboard_corners でコーナーを検出し、corner_sub_pix で精緻化する
+fn find_corners_classic(
+    gray: &Mat,
+    chessboard_size: Size,
+    objp: &Vector<Point3f>,
+    criteria: core::TermCriteria,
+    corner_sub_pix_window_width: i32,
+    corner_sub_pix_window_height: i32,
+    corner_sub_pix_zero_zone: i32,
+) -> opencv::Result<Option<DetectedCorners>> {
+    let mut corners = Vector::<Point2f>::new();
+    let found = calib3d::find_chessboard_corners(
+        gray,
+        chessboard_size,
+        &mut corners,
+        calib3d::CALIB_CB_ADAPTIVE_THRESH
+            | calib3d::CALIB_CB_FAST_CHECK
+            | calib3d::CALIB_CB_NORMALIZE_IMAGE,
+    )?;
+
+    if !found {
+        return Ok(None);
+    }
+
+    imgproc::corner_sub_pix(
+        gray,
+        &mut corners,
+        Size::new(corner_sub_pix_window_width, corner_sub_pix_window_height),
+        Size::new(corner_sub_pix_zero_zone, corner_sub_pix_zero_zone),
+        criteria,
+    )?;
+
+    Ok(Some(DetectedCorners {
+        pattern_size: chessboard_size,
+        object_points: objp.clone(),
+        image_points: corners,
+    }))
+}
+
+/// find_chessboard_corners_sb でコーナーを検出する
+/// (サブピクセル精度で返されるため corner_sub_pix は不要)
+fn find_corners_sector_based(
+    gray: &Mat,
+    chessboard_size: Size,
+    objp: &Vector<Point3f>,
+    flags: i32,
+) -> opencv::Result<Option<DetectedCorners>> {
+    let mut corners = Vector::<Point2f>::new();
+    let found = calib3d::find_chessboard_corners_sb(gray, chessboard_size, &mut corners, flags)?;
+
+    Ok(found.then(|| DetectedCorners {
+        pattern_size: chessboard_size,
+        object_points: objp.clone(),
+        image_points: corners,
+    }))
+}
+
+/// マーカー付きボードの見えている部分だけを検出する
+/// 物体座標はマーカーの原点 (meta = 4) を基準とするため、ビュー間で一貫した座標系になる
+fn find_corners_partial(
+    gray: &Mat,
+    chessboard_size: Size,
+    spacing: f32,
+    min_size: Size,
+    flags: i32,
+) -> opencv::Result<PartialBoard> {
+    const META_PATTERN_ORIGIN: u8 = 4;
+
+    let mut corners = Vector::<Point2f>::new();
+    let mut meta = Mat::default();
+    let found = calib3d::find_chessboard_corners_sb_with_meta(gray, min_size, &mut corners, flags, &mut meta)?;
+    if !found {
+        return Ok(PartialBoard::NotFound);
+    }
+
+    // 検出されたグリッドは meta と同じサイズ (columns, rows)
+    let pattern_size = Size::new(meta.cols(), meta.rows());
+    if pattern_size.width > chessboard_size.width || pattern_size.height > chessboard_size.height {
+        return Ok(PartialBoard::Rejected(format!(
+            "the detected grid {}x{} is larger than the board {}x{}",
+            pattern_size.width, pattern_size.height, chessboard_size.width, chessboard_size.height
+        )));
+    }
+    if corners.len() != (pattern_size.width * pattern_size.height) as usize {
+        return Ok(PartialBoard::Rejected(format!(
+            "{} corners do not match the detected grid {}x{}",
+            corners.len(),
+            pattern_size.width,
+            pattern_size.height
+        )));
+    }
+
+    // マーカーが見えていない場合は座標系を決められないため除外する
+    let mut origin = None;
+    for row in 0..pattern_size.height {
+        for col in 0..pattern_size.width {
+            if *meta.at_2d::<u8>(row, col)? == META_PATTERN_ORIGIN {
+                origin = Some((col, row));
+            }
         }
-        for tvec in tvecs {
-            let tvec_vec = (0..tvec.total() as usize)
-                .map(|i| *tvec.at::<f64>(i as i32).unwrap())
-                .collect::<Vec<f64>>();
-            translation_vectors.push(tvec_vec);
+    }
+    let Some((origin_col, origin_row)) = origin else {
+        return Ok(PartialBoard::Rejected("the origin marker is not visible".to_string()));
+    };
+
+    let mut object_points = Vector::<Point3f>::new();
+    for row in 0..pattern_size.height {
+        for col in 0..pattern_size.width {
+            object_points.push(Point3f::new(
+                (col - origin_col) as f32 * spacing,
+                (row - origin_row) as f32 * spacing,
+                0.0,
+            ));
         }
+    }
 
-        let calibration = CameraCalibration {
-            camera_matrix: camera_matrix_vec,
-            distortion_parameters: dist_coeffs_vec,
-            rotation_vectors,
-            translation_vectors,
-            total_error: error,
-        };
+    Ok(PartialBoard::Found(DetectedCorners {
+        pattern_size,
+        object_points,
+        image_points: corners,
+    }))
+}
 
-        let json_string = serde_json::to_string_pretty(&calibration)?;
-        let mut file = File::create(filename)?;
-        file.write_all(json_string.as_bytes())?;
+/// プレビューウィンドウを開く
+fn open_preview(preview: &PreviewConfig) -> opencv::Result<()> {
+    if !preview.enabled {
+        return Ok(());
+    }
 
-        Ok(())
+    highgui::named_window(&preview.window_title, highgui::WINDOW_NORMAL)?;
+    highgui::resize_window(&preview.window_title, preview.window_width, preview.window_height)
+}
+
+/// 検出結果にファイル名を重ねて表示する
+fn show_preview(preview: &PreviewConfig, img: &mut Mat, file_name: &str) -> opencv::Result<()> {
+    // Read each file and display the filename on the window
+    let org = core::Point::new(preview.text_point.0, preview.text_point.1);
+    let font_face = imgproc::FONT_HERSHEY_SIMPLEX;
+    let (b, g, r, a) = preview.text_color;
+    let color = core::Scalar::new(b, g, r, a);
+    let thickness = 2;
+    imgproc::put_text(img, file_name, org, font_face, preview.text_font_scale, color, thickness, imgproc::LINE_AA, false)?;
+
+    highgui::imshow(&preview.window_title, &*img)?;
+    highgui::wait_key(preview.wait_key_delay)?;
+    Ok(())
+}
+
+/// プレビューウィンドウを閉じる
+fn close_preview(preview: &PreviewConfig) -> opencv::Result<()> {
+    if !preview.enabled {
+        return Ok(());
     }
+
+    highgui::destroy_all_windows()
 }
@@ -0,0 +1,634 @@
+use opencv::{
+    calib3d,
+    core::{self, Mat, Size, Vector},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_calibration::{CalibrationOutput, CameraCalibration},
+    command_line::CalibrationPattern,
+    error::{CalibrationError, Result},
+    evaluation::ReprojectionStatistics,
+    file::CustomFile,
+    target::Units,
+};
+
+/// calibration.json の形式のバージョン
+/// 形式を変えた場合は上げ、`CalibrationResult::load` で古い形式を読めるようにする
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 内部パラメータ [px]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Intrinsics {
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    /// 画素の軸のせん断 (calibrate_camera では常に 0)
+    #[serde(default)]
+    pub skew: f64,
+}
+
+/// 歪みモデルとその係数
+///
+/// 係数は OpenCV の歪み係数ベクトルと同じ順序で、モデルは係数の数から決まる
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "model", content = "coefficients", rename_all = "snake_case")]
+pub enum Distortion {
+    /// 歪みなし
+    None,
+    /// k1, k2, p1, p2, k3
+    BrownConrady([f64; 5]),
+    /// k1, k2, p1, p2, k3, k4, k5, k6 (CALIB_RATIONAL_MODEL)
+    Rational([f64; 8]),
+    /// Rational に s1, s2, s3, s4 を加えたもの (CALIB_THIN_PRISM_MODEL)
+    ThinPrism([f64; 12]),
+    /// ThinPrism に τx, τy を加えたもの (CALIB_TILTED_MODEL)
+    Tilted([f64; 14]),
+}
+
+/// 1ビューのターゲットの姿勢 (ターゲット座標系からカメラ座標系への変換)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Pose {
+    /// このビューの画像のファイル名
+    pub image_name: String,
+    /// 回転ベクトル (Rodrigues)
+    pub rvec: [f64; 3],
+    /// 並進ベクトル (ターゲット記述の単位)
+    pub tvec: [f64; 3],
+    /// このビューの RMS 再投影誤差 [px]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reprojection_error: Option<f64>,
+}
+
+/// 画像サイズ [px]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ImageSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// キャリブレーションの品質と作成時の情報
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Metadata {
+    /// calibrate_camera が返す全点の RMS 再投影誤差 [px]
+    /// 旧形式には無いため、旧形式から変換した場合は None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rms: Option<f64>,
+    /// 以前の形式と同じ誤差 [px] (ビューごとの RMS の平均)。全点の RMS などは `reprojection` にある
+    pub total_error: f64,
+    /// compute_reprojection_error で求めた再投影誤差の統計 (旧形式から変換した場合は無い)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reprojection: Option<ReprojectionStatistics>,
+    /// 内部パラメータの標準偏差 (fx, fy, cx, cy, 歪み係数の順)
+    #[serde(default)]
+    pub std_intrinsics: Vec<f64>,
+    /// キャリブレーションパターン (旧形式から変換した場合は不明)
+    #[serde(default)]
+    pub pattern: Option<CalibrationPattern>,
+    /// 並進ベクトルの単位。None の場合はターゲット記述の spacing と同じ単位
+    #[serde(default)]
+    pub units: Option<Units>,
+    /// 作成日時 (UTC, RFC 3339)
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// 作成したツールの名前とバージョン
+    #[serde(default)]
+    pub tool: String,
+    /// 旧形式から変換した場合の変換元のファイル
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrated_from: Option<String>,
+}
+
+/// キャリブレーションの結果 (calibration.json)
+///
+/// 内部パラメータと画像サイズの単位は画素
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CalibrationResult {
+    /// ファイル形式のバージョン (`SCHEMA_VERSION`)
+    pub schema_version: u32,
+    pub image_size: ImageSize,
+    pub intrinsics: Intrinsics,
+    pub distortion: Distortion,
+    /// キャリブレーションに使ったビューごとの姿勢
+    pub poses: Vec<Pose>,
+    pub metadata: Metadata,
+}
+
+impl Intrinsics {
+    /// カメラ行列 (3x3) から作成する
+    pub fn from_mat(camera_matrix: &Mat) -> Result<Self> {
+        if camera_matrix.rows() != 3 || camera_matrix.cols() != 3 {
+            return Err(CalibrationError::Calibration(format!(
+                "camera matrix must be 3x3, got {}x{}",
+                camera_matrix.rows(),
+                camera_matrix.cols()
+            )));
+        }
+        let at = |row: i32, col: i32| camera_matrix.at_2d::<f64>(row, col).copied();
+        Ok(Self {
+            fx: at(0, 0)?,
+            fy: at(1, 1)?,
+            cx: at(0, 2)?,
+            cy: at(1, 2)?,
+            skew: at(0, 1)?,
+        })
+    }
+
+    pub fn to_array(&self) -> [[f64; 3]; 3] {
+        [[self.fx, self.skew, self.cx], [0.0, self.fy, self.cy], [0.0, 0.0, 1.0]]
+    }
+
+    /// カメラ行列を Mat (3x3, CV_64F) で返す
+    pub fn to_mat(&self) -> Result<Mat> {
+        Ok(Mat::from_slice_2d(&self.to_array())?)
+    }
+
+    #[cfg(feature = "nalgebra")]
+    pub fn to_matrix3(&self) -> nalgebra::Matrix3<f64> {
+        nalgebra::Matrix3::new(self.fx, self.skew, self.cx, 0.0, self.fy, self.cy, 0.0, 0.0, 1.0)
+    }
+
+    #[cfg(feature = "nalgebra")]
+    pub fn from_matrix3(camera_matrix: &nalgebra::Matrix3<f64>) -> Self {
+        Self {
+            fx: camera_matrix[(0, 0)],
+            fy: camera_matrix[(1, 1)],
+            cx: camera_matrix[(0, 2)],
+            cy: camera_matrix[(1, 2)],
+            skew: camera_matrix[(0, 1)],
+        }
+    }
+}
+
+impl Distortion {
+    /// OpenCV の歪み係数ベクトルから作成する
+    /// 係数の数は 0, 4, 5, 8, 12, 14 のいずれか (4 の場合は k3 = 0 とする)
+    pub fn from_coefficients(coefficients: &[f64]) -> Result<Self> {
+        Ok(match coefficients.len() {
+            0 => Distortion::None,
+            4 | 5 => Distortion::BrownConrady(padded(coefficients)),
+            8 => Distortion::Rational(padded(coefficients)),
+            12 => Distortion::ThinPrism(padded(coefficients)),
+            14 => Distortion::Tilted(padded(coefficients)),
+            n => {
+                return Err(CalibrationError::Calibration(format!(
+                    "unsupported number of distortion coefficients: {} (expected 0, 4, 5, 8, 12 or 14)",
+                    n
+                )))
+            }
+        })
+    }
+
+    /// 歪み係数 (1xN, CV_64F) から作成する
+    pub fn from_mat(dist_coeffs: &Mat) -> Result<Self> {
+        if dist_coeffs.empty() {
+            return Ok(Distortion::None);
+        }
+        Self::from_coefficients(dist_coeffs.data_typed::<f64>()?)
+    }
+
+    /// OpenCV の順序の歪み係数
+    pub fn coefficients(&self) -> &[f64] {
+        match self {
+            Distortion::None => &[],
+            Distortion::BrownConrady(c) => c,
+            Distortion::Rational(c) => c,
+            Distortion::ThinPrism(c) => c,
+            Distortion::Tilted(c) => c,
+        }
+    }
+
+    /// 歪み係数を Mat (1xN, CV_64F) で返す。歪みなしの場合は空の Mat
+    pub fn to_mat(&self) -> Result<Mat> {
+        if self.coefficients().is_empty() {
+            return Ok(Mat::default());
+        }
+        Ok(Mat::from_slice(self.coefficients())?.try_clone()?)
+    }
+
+    #[cfg(feature = "nalgebra")]
+    pub fn to_dvector(&self) -> nalgebra::DVector<f64> {
+        nalgebra::DVector::from_column_slice(self.coefficients())
+    }
+}
+
+impl Pose {
+    /// 回転ベクトルと並進ベクトル (3x1 または 1x3, CV_64F) から作成する
+    pub fn from_mats(image_name: &str, rvec: &Mat, tvec: &Mat) -> Result<Self> {
+        Ok(Self {
+            image_name: image_name.to_string(),
+            rvec: vector3(rvec)?,
+            tvec: vector3(tvec)?,
+            reprojection_error: None,
+        })
+    }
+
+    /// 回転ベクトル (3x1, CV_64F)
+    pub fn rvec_mat(&self) -> Result<Mat> {
+        Ok(Mat::from_slice(&self.rvec)?.t()?.to_mat()?)
+    }
+
+    /// 並進ベクトル (3x1, CV_64F)
+    pub fn tvec_mat(&self) -> Result<Mat> {
+        Ok(Mat::from_slice(&self.tvec)?.t()?.to_mat()?)
+    }
+
+    /// 回転行列
+    pub fn rotation_matrix(&self) -> Result<[[f64; 3]; 3]> {
+        let mut rotation = Mat::default();
+        calib3d::rodrigues(&self.rvec_mat()?, &mut rotation, &mut Mat::default())?;
+        let mut matrix = [[0.0; 3]; 3];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = *rotation.at_2d::<f64>(i as i32, j as i32)?;
+            }
+        }
+        Ok(matrix)
+    }
+
+    #[cfg(feature = "nalgebra")]
+    pub fn to_isometry3(&self) -> nalgebra::Isometry3<f64> {
+        let [tx, ty, tz] = self.tvec;
+        let [rx, ry, rz] = self.rvec;
+        nalgebra::Isometry3::new(nalgebra::Vector3::new(tx, ty, tz), nalgebra::Vector3::new(rx, ry, rz))
+    }
+
+    #[cfg(feature = "nalgebra")]
+    pub fn from_isometry3(image_name: &str, isometry: &nalgebra::Isometry3<f64>) -> Self {
+        let rvec = isometry.rotation.scaled_axis();
+        let tvec = isometry.translation.vector;
+        Self {
+            image_name: image_name.to_string(),
+            rvec: [rvec.x, rvec.y, rvec.z],
+            tvec: [tvec.x, tvec.y, tvec.z],
+            reprojection_error: None,
+        }
+    }
+}
+
+impl ImageSize {
+    pub fn to_size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl From<Size> for ImageSize {
+    fn from(size: Size) -> Self {
+        Self { width: size.width, height: size.height }
+    }
+}
+
+impl CalibrationResult {
+    /// calibrate_camera の結果から作成する
+    /// `image_names` はビューの順序で与え、`units` はターゲット記述の単位を与える
+    pub fn new(
+        output: &CalibrationOutput,
+        statistics: &ReprojectionStatistics,
+        frame_size: Size,
+        image_names: &[String],
+        pattern: CalibrationPattern,
+        units: Option<Units>,
+    ) -> Result<Self> {
+        let mut poses = Vec::with_capacity(output.rvecs.len());
+        for (i, (rvec, tvec)) in output.rvecs.iter().zip(output.tvecs.iter()).enumerate() {
+            let image_name = image_names.get(i).map(String::as_str).unwrap_or_default();
+            let mut pose = Pose::from_mats(image_name, &rvec, &tvec)?;
+            pose.reprojection_error = output.per_view_errors.get(i).copied();
+            poses.push(pose);
+        }
+
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            image_size: frame_size.into(),
+            intrinsics: Intrinsics::from_mat(&output.camera_matrix)?,
+            distortion: Distortion::from_mat(&output.dist_coeffs)?,
+            poses,
+            metadata: Metadata {
+                rms: Some(output.rms),
+                total_error: statistics.legacy_total_error,
+                reprojection: Some(statistics.clone()),
+                std_intrinsics: output.std_intrinsics.clone(),
+                pattern: Some(pattern),
+                units,
+                created_at: Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+                tool: tool_name(),
+                migrated_from: None,
+            },
+        })
+    }
+
+    /// JSON から読み込む
+    ///
+    /// `schema_version` の無い旧形式のファイルも読み込める。
+    /// 旧形式には画像サイズが無いため、主点が画像の中心にあるものとして推定する
+    pub fn load(file_path: &str) -> Result<Self> {
+        let json: serde_json::Value = CustomFile::read_json(file_path)?;
+        match json.get("schema_version").and_then(|version| version.as_u64()) {
+            Some(version) if version > SCHEMA_VERSION as u64 => Err(CalibrationError::serialization(
+                file_path,
+                format!(
+                    "schema version {} is newer than the supported version {}. Update the tool",
+                    version, SCHEMA_VERSION
+                ),
+            )),
+            Some(_) => serde_json::from_value(json).map_err(|e| CalibrationError::serialization(file_path, e)),
+            None => {
+                let legacy: CameraCalibration =
+                    serde_json::from_value(json).map_err(|e| CalibrationError::serialization(file_path, e))?;
+                let result = Self::from_legacy(&legacy, None, file_path)?;
+                eprintln!(
+                    "'{}' uses the legacy calibration format without an image size (assuming {}x{}). Run `migrate` to upgrade it",
+                    file_path, result.image_size.width, result.image_size.height
+                );
+                Ok(result)
+            }
+        }
+    }
+
+    /// 旧形式のキャリブレーション結果から変換する
+    /// `image_size` が None の場合は主点から推定する
+    pub fn from_legacy(legacy: &CameraCalibration, image_size: Option<ImageSize>, file_path: &str) -> Result<Self> {
+        let intrinsics = Intrinsics::from_mat(&legacy.camera_matrix()?)?;
+        let image_size = image_size.unwrap_or(ImageSize {
+            width: (2.0 * intrinsics.cx).round() as i32,
+            height: (2.0 * intrinsics.cy).round() as i32,
+        });
+
+        let mut poses = Vec::new();
+        for (rvec, tvec) in legacy.rotation_vectors.iter().zip(legacy.translation_vectors.iter()) {
+            // 旧形式にはビューと画像の対応が無い
+            poses.push(Pose::from_mats("", &Mat::from_slice(rvec)?.try_clone()?, &Mat::from_slice(tvec)?.try_clone()?)?);
+        }
+
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            image_size,
+            intrinsics,
+            distortion: Distortion::from_mat(&legacy.dist_coeffs()?)?,
+            poses,
+            metadata: Metadata {
+                // 旧形式の total_error はビューごとの誤差の平均で、全点の RMS ではない
+                rms: None,
+                total_error: legacy.total_error,
+                reprojection: None,
+                std_intrinsics: Vec::new(),
+                pattern: None,
+                units: None,
+                created_at: None,
+                tool: tool_name(),
+                migrated_from: Some(file_path.to_string()),
+            },
+        })
+    }
+
+    /// JSON で保存する。`load` で同じ値に読み戻せる
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        CustomFile::write_json(file_path, self)
+    }
+
+    /// ビューごとの回転ベクトルと並進ベクトル
+    pub fn pose_mats(&self) -> Result<(Vector<Mat>, Vector<Mat>)> {
+        let mut rvecs = Vector::<Mat>::new();
+        let mut tvecs = Vector::<Mat>::new();
+        for pose in &self.poses {
+            rvecs.push(pose.rvec_mat()?);
+            tvecs.push(pose.tvec_mat()?);
+        }
+        Ok((rvecs, tvecs))
+    }
+
+    /// OpenCV の FileStorage 形式 (YAML / XML / JSON) で書き出す
+    /// 形式は拡張子から決まる
+    pub fn write_file_storage(&self, file_path: &str) -> Result<()> {
+        let mut fs = core::FileStorage::new(file_path, core::FileStorage_WRITE, "")?;
+        if !fs.is_opened()? {
+            return Err(CalibrationError::io(
+                file_path,
+                std::io::Error::other("failed to open the file for writing"),
+            ));
+        }
+
+        fs.write_i32("image_width", self.image_size.width)?;
+        fs.write_i32("image_height", self.image_size.height)?;
+        fs.write_mat("camera_matrix", &self.intrinsics.to_mat()?)?;
+        fs.write_mat("distortion_coefficients", &self.distortion.to_mat()?)?;
+        fs.write_i32("nr_of_frames", self.poses.len() as i32)?;
+        fs.write_f64("avg_reprojection_error", self.metadata.total_error)?;
+        if !self.poses.is_empty() {
+            // 1行に rvec, tvec を並べる (OpenCV のサンプルと同じ extrinsic_parameters の形式)
+            let extrinsics: Vec<Vec<f64>> = self
+                .poses
+                .iter()
+                .map(|pose| pose.rvec.iter().chain(pose.tvec.iter()).copied().collect())
+                .collect();
+            fs.write_mat("extrinsic_parameters", &Mat::from_slice_2d(&extrinsics)?)?;
+        }
+
+        Ok(fs.release()?)
+    }
+}
+
+/// metadata.tool に書くツールの名前とバージョン
+fn tool_name() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// 係数を長さ N の配列にし、足りない係数は 0 とする
+fn padded<const N: usize>(coefficients: &[f64]) -> [f64; N] {
+    let mut values = [0.0; N];
+    values[..coefficients.len()].copy_from_slice(coefficients);
+    values
+}
+
+/// 3要素の Mat (CV_64F) を配列にする
+fn vector3(mat: &Mat) -> Result<[f64; 3]> {
+    let values = mat.data_typed::<f64>()?;
+    match values {
+        [x, y, z] => Ok([*x, *y, *z]),
+        _ => Err(CalibrationError::Calibration(format!(
+            "expected a vector with 3 elements, got {}",
+            values.len()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// テストごとに別の一時ファイル
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("camera_calibrate_{}_{}.json", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn sample_result() -> CalibrationResult {
+        CalibrationResult {
+            schema_version: SCHEMA_VERSION,
+            image_size: ImageSize { width: 4032, height: 3024 },
+            intrinsics: Intrinsics {
+                fx: 3286.4629841607984,
+                fy: 3271.6754503180246,
+                cx: 2023.5768869100714,
+                cy: 1503.2101514954427,
+                skew: 0.0,
+            },
+            // 10進数で短く表せない値も含める
+            distortion: Distortion::Rational([
+                0.1 + 0.2,
+                -2.349647809603689,
+                0.0007015239366941092,
+                0.0005142872882248564,
+                6.576827620207052,
+                1e-300,
+                -f64::EPSILON,
+                std::f64::consts::PI,
+            ]),
+            poses: vec![
+                Pose {
+                    image_name: "calib01.jpeg".to_string(),
+                    rvec: [1.2968106206837728, -0.07194663305638405, 2.8640500618882805],
+                    tvec: [-98.13, -64.0, 1.0 / 3.0],
+                    reprojection_error: Some(0.21),
+                },
+                Pose {
+                    image_name: "calib02.jpeg".to_string(),
+                    rvec: [0.02, -0.31, 0.01],
+                    tvec: [12.5, 7.25, 401.0],
+                    reprojection_error: None,
+                },
+            ],
+            metadata: Metadata {
+                rms: Some(0.24),
+                total_error: 0.22,
+                reprojection: Some(ReprojectionStatistics {
+                    view_count: 2,
+                    point_count: 108,
+                    failed_view_count: 0,
+                    rms: 0.24,
+                    mean_view_rms: 0.22,
+                    median_view_rms: 0.22,
+                    max_view_rms: 0.41,
+                    max_point_error: 1.37,
+                    legacy_total_error: 0.22,
+                }),
+                std_intrinsics: vec![1.9, 1.8, 1.2, 1.1, 0.002],
+                pattern: Some(CalibrationPattern::ChessBoard),
+                units: Some(Units::Mm),
+                created_at: Some("2026-10-18T09:12:44Z".to_string()),
+                tool: tool_name(),
+                migrated_from: None,
+            },
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip_exactly() {
+        let path = temp_path("round_trip");
+        let result = sample_result();
+        result.save(&path).unwrap();
+        let loaded = CalibrationResult::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, result);
+        // PartialEq に加えてビット単位でも一致すること (float_roundtrip)
+        for (a, b) in loaded.distortion.coefficients().iter().zip(result.distortion.coefficients()) {
+            assert_eq!(a.to_bits(), b.to_bits());
+        }
+        assert_eq!(loaded.poses[0].tvec[2].to_bits(), (1.0f64 / 3.0).to_bits());
+    }
+
+    #[test]
+    fn distortion_model_follows_the_coefficient_count() {
+        let cases = [
+            (0, "none"),
+            (4, "brown_conrady"),
+            (5, "brown_conrady"),
+            (8, "rational"),
+            (12, "thin_prism"),
+            (14, "tilted"),
+        ];
+        for (count, model) in cases {
+            let coefficients: Vec<f64> = (1..=count).map(|i| i as f64 * 0.01).collect();
+            let distortion = Distortion::from_coefficients(&coefficients).unwrap();
+            assert_eq!(serde_json::to_value(&distortion).unwrap()["model"], model);
+
+            // 4 係数の場合は k3 = 0 を補う
+            let expected_count = if count == 4 { 5 } else { count };
+            assert_eq!(distortion.coefficients().len(), expected_count);
+            assert_eq!(&distortion.coefficients()[..count], coefficients.as_slice());
+            assert!(distortion.coefficients()[count..].iter().all(|c| *c == 0.0));
+
+            let mat = distortion.to_mat().unwrap();
+            if count == 0 {
+                assert!(mat.empty());
+            } else {
+                assert_eq!(mat.data_typed::<f64>().unwrap(), distortion.coefficients());
+            }
+            assert_eq!(Distortion::from_mat(&mat).unwrap(), distortion);
+        }
+    }
+
+    #[test]
+    fn unsupported_coefficient_count_is_rejected() {
+        assert!(Distortion::from_coefficients(&[0.0; 6]).is_err());
+    }
+
+    const LEGACY_JSON: &str = r#"{
+        "camera_matrix": [[3286.46, 0.0, 2016.0], [0.0, 3271.67, 1512.0], [0.0, 0.0, 1.0]],
+        "distortion_parameters": [0.27, -2.35, 0.0007, 0.0005, 6.58],
+        "rotation_vectors": [[1.29, -0.07, 2.86], [0.02, -0.31, 0.01]],
+        "translation_vectors": [[-98.1, -64.0, 412.5], [12.5, 7.25, 401.0]],
+        "total_error": 0.22
+    }"#;
+
+    #[test]
+    fn load_converts_the_legacy_format() {
+        let path = temp_path("legacy");
+        std::fs::write(&path, LEGACY_JSON).unwrap();
+        let loaded = CalibrationResult::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.schema_version, SCHEMA_VERSION);
+        // 画像サイズは主点を中心として推定する
+        assert_eq!(loaded.image_size, ImageSize { width: 4032, height: 3024 });
+        assert_eq!(
+            loaded.intrinsics,
+            Intrinsics { fx: 3286.46, fy: 3271.67, cx: 2016.0, cy: 1512.0, skew: 0.0 }
+        );
+        assert_eq!(loaded.distortion, Distortion::BrownConrady([0.27, -2.35, 0.0007, 0.0005, 6.58]));
+        assert_eq!(loaded.poses.len(), 2);
+        assert_eq!(loaded.poses[1].rvec, [0.02, -0.31, 0.01]);
+        assert_eq!(loaded.poses[1].tvec, [12.5, 7.25, 401.0]);
+        assert!(loaded.poses.iter().all(|pose| pose.image_name.is_empty()));
+        assert_eq!(loaded.metadata.rms, None);
+        assert_eq!(loaded.metadata.total_error, 0.22);
+        assert_eq!(loaded.metadata.reprojection, None);
+        assert_eq!(loaded.metadata.pattern, None);
+        assert_eq!(loaded.metadata.migrated_from.as_deref(), Some(path.as_str()));
+    }
+
+    #[test]
+    fn from_legacy_prefers_the_given_image_size() {
+        let legacy: CameraCalibration = serde_json::from_str(LEGACY_JSON).unwrap();
+        let image_size = ImageSize { width: 4000, height: 3000 };
+        let result = CalibrationResult::from_legacy(&legacy, Some(image_size), "legacy.json").unwrap();
+        assert_eq!(result.image_size, image_size);
+    }
+
+    #[test]
+    fn load_rejects_a_newer_schema_version() {
+        let path = temp_path("newer_schema");
+        let mut json = serde_json::to_value(sample_result()).unwrap();
+        json["schema_version"] = serde_json::json!(SCHEMA_VERSION + 1);
+        std::fs::write(&path, json.to_string()).unwrap();
+        let loaded = CalibrationResult::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(CalibrationError::Serialization { .. })));
+    }
+}
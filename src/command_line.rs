@@ -1,17 +1,318 @@
-use clap::{Parser, ValueEnum};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::target::Units;
 
 #[derive(Parser, Debug)]
 #[command(version, about, flatten_help = true)]
 pub struct Args {
-    /// select chessboard pattern
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// detect the calibration pattern and calibrate the camera
+    Calibrate(RunArgs),
+    /// detect the calibration pattern without calibrating
+    Detect(RunArgs),
+    /// undistort images with a saved calibration
+    Undistort(UndistortArgs),
+    /// evaluate a saved calibration on a set of pattern images
+    Evaluate(EvaluateArgs),
+    /// convert a calibration file to OpenCV FileStorage format (YAML, XML or JSON)
+    Convert(ConvertArgs),
+    /// show a summary of a saved calibration
+    Report(ReportArgs),
+    /// render the lens distortion model of a saved calibration as images
+    Distortion(DistortionArgs),
+    /// upgrade a calibration file in the legacy format to the current schema
+    Migrate(MigrateArgs),
+    /// compare two calibration files and check whether the camera has changed
+    Compare(CompareArgs),
+    /// estimate the pose of the target in a single image with a saved calibration
+    Pose(PoseArgs),
+    /// generate a printable calibration target (SVG, PNG, PDF) and its target description
+    GenerateTarget(GenerateTargetArgs),
+}
+
+/// detection and calibration options shared by calibrate, detect and evaluate
+#[derive(ClapArgs, Debug)]
+pub struct RunArgs {
+    /// run configuration file (TOML)
+    #[arg(long = "config")]
+    pub config: Option<String>,
+
+    /// select calibration pattern (charuco is not supported yet)
     #[arg(
-        short = 'c',
-        long = "calibrate",
+        short = 'p',
+        long = "pattern",
         value_enum,
-        default_value = "default",
         value_parser = validate_calibrate
     )]
-    pub calibrate: Option<CalibrationPattern>,
+    pub pattern: Option<CalibrationPattern>,
+
+    /// directory of the calibration images, or a glob pattern such as "./img/**/*.png"
+    #[arg(long = "dataset")]
+    pub dataset: Option<String>,
+
+    /// image file extensions to read, case-insensitive (default: jpeg,jpg,png,tif,tiff,bmp,webp)
+    #[arg(long = "extensions", value_delimiter = ',')]
+    pub extensions: Option<Vec<String>>,
+
+    /// also read images in subdirectories of the dataset directory
+    #[arg(short = 'r', long = "recursive")]
+    pub recursive: bool,
+
+    /// calibration video (MP4, MKV, ...) used instead of the dataset images
+    #[arg(long = "video")]
+    pub video: Option<String>,
+
+    /// use every Nth frame of the video
+    #[arg(long = "frame-step")]
+    pub frame_step: Option<usize>,
+
+    /// target description file (default: target.json in the dataset directory)
+    #[arg(short = 't', long = "target")]
+    pub target: Option<String>,
+
+    /// output directory
+    #[arg(short = 'o', long = "output-dir")]
+    pub output_dir: Option<String>,
+
+    /// do not show the detection results in a window
+    #[arg(long = "no-preview")]
+    pub no_preview: bool,
+
+    /// skip the pre-calibration checks of the number of views, image coverage, board tilt and distance
+    #[arg(long = "skip-checks")]
+    pub skip_checks: bool,
+
+    /// fail instead of warning when a pre-calibration check does not pass
+    #[arg(long = "strict-checks")]
+    pub strict_checks: bool,
+
+    /// detect every image again instead of reusing the cached detections
+    #[arg(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// select chessboard corner detector
+    #[arg(
+        short = 'd',
+        long = "detector",
+        value_enum,
+        value_parser = validate_detector
+    )]
+    pub detector: Option<ChessboardDetector>,
+
+    /// sector based detector: run an exhaustive search to improve detection rate
+    #[arg(long = "sb-exhaustive")]
+    pub sb_exhaustive: bool,
+
+    /// sector based detector: up sample the image to improve sub-pixel accuracy
+    #[arg(long = "sb-accuracy")]
+    pub sb_accuracy: bool,
+
+    /// sector based detector: allow the detected pattern to be larger than the board size
+    #[arg(long = "sb-larger")]
+    pub sb_larger: bool,
+
+    /// sector based detector: require the pattern to have a marker
+    #[arg(long = "sb-marker")]
+    pub sb_marker: bool,
+
+    /// sector based detector: accept partially visible boards with origin markers
+    #[arg(long = "partial")]
+    pub partial: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct UndistortArgs {
+    /// calibration file (calibration.json)
+    #[arg(short = 'k', long = "calibration")]
+    pub calibration: String,
+
+    /// image file or directory of images to undistort
+    #[arg(short = 'i', long = "input")]
+    pub input: String,
+
+    /// output directory
+    #[arg(short = 'o', long = "output-dir", default_value = "./out/undistorted")]
+    pub output_dir: String,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct EvaluateArgs {
+    /// calibration file (calibration.json)
+    #[arg(short = 'k', long = "calibration")]
+    pub calibration: String,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct ConvertArgs {
+    /// calibration file (calibration.json)
+    #[arg(short = 'i', long = "input")]
+    pub input: String,
+
+    /// output file (.yml, .yaml, .xml or .json)
+    #[arg(short = 'o', long = "output")]
+    pub output: String,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct ReportArgs {
+    /// calibration file (calibration.json)
+    #[arg(short = 'k', long = "calibration")]
+    pub calibration: String,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct DistortionArgs {
+    /// calibration file (calibration.json)
+    #[arg(short = 'k', long = "calibration")]
+    pub calibration: String,
+
+    /// image width in pixels (default: image size of the calibration)
+    #[arg(long = "width")]
+    pub width: Option<i32>,
+
+    /// image height in pixels (default: image size of the calibration)
+    #[arg(long = "height")]
+    pub height: Option<i32>,
+
+    /// output directory
+    #[arg(short = 'o', long = "output-dir", default_value = "./out/distortion")]
+    pub output_dir: String,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct CompareArgs {
+    /// reference calibration file (e.g. before maintenance)
+    #[arg(short = 'a', long = "reference")]
+    pub reference: String,
+
+    /// calibration file to compare with the reference
+    #[arg(short = 'b', long = "candidate")]
+    pub candidate: String,
+
+    /// maximum allowed displacement between the undistortion mappings in pixels
+    #[arg(long = "max-displacement", default_value_t = 1.0)]
+    pub max_displacement: f64,
+
+    /// maximum allowed parameter change in combined standard deviations
+    #[arg(long = "max-sigma", default_value_t = 3.0)]
+    pub max_sigma: f64,
+
+    /// write the comparison to a JSON file
+    #[arg(short = 'o', long = "output")]
+    pub output: Option<String>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct PoseArgs {
+    /// calibration file (calibration.json)
+    #[arg(short = 'k', long = "calibration")]
+    pub calibration: String,
+
+    /// image of the target
+    #[arg(short = 'i', long = "image")]
+    pub image: String,
+
+    /// use solvePnPRansac to reject outlier points
+    #[arg(long = "ransac")]
+    pub ransac: bool,
+
+    /// RANSAC: maximum reprojection error of an inlier in pixels
+    #[arg(long = "ransac-threshold", default_value_t = 8.0)]
+    pub ransac_threshold: f32,
+
+    /// RANSAC: number of iterations
+    #[arg(long = "ransac-iterations", default_value_t = 100)]
+    pub ransac_iterations: i32,
+
+    /// length of the drawn axes in target units (default: 3 * spacing)
+    #[arg(long = "axis-length")]
+    pub axis_length: Option<f64>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct MigrateArgs {
+    /// calibration file in the legacy format
+    #[arg(short = 'i', long = "input")]
+    pub input: String,
+
+    /// output calibration file
+    #[arg(short = 'o', long = "output")]
+    pub output: String,
+
+    /// image width in pixels (default: twice the principal point x)
+    #[arg(long = "width")]
+    pub width: Option<i32>,
+
+    /// image height in pixels (default: twice the principal point y)
+    #[arg(long = "height")]
+    pub height: Option<i32>,
+
+    /// calibration pattern used for the calibration
+    #[arg(short = 'p', long = "pattern", value_enum, value_parser = validate_calibrate)]
+    pub pattern: Option<CalibrationPattern>,
+
+    /// units of the target (and of the translation vectors)
+    #[arg(long = "units", value_enum)]
+    pub units: Option<Units>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct GenerateTargetArgs {
+    /// target pattern
+    #[arg(short = 'p', long = "pattern", value_enum, value_parser = validate_calibrate)]
+    pub pattern: CalibrationPattern,
+
+    /// number of rows (inner corners for chessboard, circles, squares for charuco, tags for aprilgrid)
+    #[arg(long = "rows")]
+    pub rows: i32,
+
+    /// number of columns (inner corners for chessboard, circles, squares for charuco, tags for aprilgrid)
+    #[arg(long = "cols")]
+    pub cols: i32,
+
+    /// square size, circle spacing or tag size
+    #[arg(long = "spacing")]
+    pub spacing: f64,
+
+    /// charuco: marker size (default: 0.7 * spacing)
+    #[arg(long = "marker-size")]
+    pub marker_size: Option<f64>,
+
+    /// aprilgrid: space between tags relative to the tag size
+    #[arg(long = "tag-spacing", default_value_t = 0.3)]
+    pub tag_spacing: f64,
+
+    /// charuco: marker dictionary
+    #[arg(long = "dictionary", default_value = "DICT_5X5_100")]
+    pub dictionary: String,
+
+    /// unit of the sizes
+    #[arg(long = "units", value_enum, default_value = "mm")]
+    pub units: Units,
+
+    /// white margin around the pattern (default: spacing)
+    #[arg(long = "margin")]
+    pub margin: Option<f64>,
+
+    /// resolution of the PNG output
+    #[arg(long = "dpi", default_value_t = 300.0)]
+    pub dpi: f64,
+
+    /// output path without extension
+    #[arg(short = 'o', long = "output", default_value = "./out/target/target")]
+    pub output: String,
 }
 
 fn validate_calibrate(val: &str) -> Result<CalibrationPattern, String> {
@@ -20,17 +321,49 @@ fn validate_calibrate(val: &str) -> Result<CalibrationPattern, String> {
         "symmetric" => Ok(CalibrationPattern::SymmetricCircleGrid),
         "asymmetric" => Ok(CalibrationPattern::AsymmetricCircleGrid),
         "charuco" => Ok(CalibrationPattern::ChArUco),
+        "aprilgrid" => Ok(CalibrationPattern::AprilGrid),
+        _ => Err(format!(
+            "Invalid calibration pattern: '{}'. Allowed values are: chessboard, symmetric, asymmetric, charuco, aprilgrid.",
+            val
+        ))
+    }
+}
+
+fn validate_detector(val: &str) -> Result<ChessboardDetector, String> {
+    match val.to_lowercase().as_str() {
+        "classic" => Ok(ChessboardDetector::Classic),
+        "sb" => Ok(ChessboardDetector::SectorBased),
+        "both" => Ok(ChessboardDetector::Both),
         _ => Err(format!(
-            "Invalid calibration pattern: '{}'. Allowed values are: chessboard, symmetric, asymmetric, charuco.",
+            "Invalid chessboard detector: '{}'. Allowed values are: classic, sb, both.",
             val
         ))
     }
 }
 
-#[derive(ValueEnum, Debug, Clone, PartialEq)]
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum CalibrationPattern {
+    #[serde(rename = "chessboard")]
     ChessBoard,
+    #[serde(rename = "symmetric")]
     SymmetricCircleGrid,
+    #[serde(rename = "asymmetric")]
     AsymmetricCircleGrid,
+    #[serde(rename = "charuco")]
     ChArUco,
+    #[serde(rename = "aprilgrid")]
+    AprilGrid,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ChessboardDetector {
+    /// find_chessboard_corners + corner_sub_pix
+    #[serde(rename = "classic")]
+    Classic,
+    /// find_chessboard_corners_sb
+    #[serde(rename = "sb")]
+    SectorBased,
+    /// run both detectors and compare the results
+    #[serde(rename = "both")]
+    Both,
 }
\ No newline at end of file
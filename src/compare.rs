@@ -0,0 +1,325 @@
+use opencv::{
+    calib3d,
+    core::{Mat, Point2f, Point3f, Vector},
+    prelude::*,
+};
+use serde::Serialize;
+
+use crate::{
+    camera_model::CalibrationResult,
+    error::{CalibrationError, Result},
+};
+
+/// 歪み補正のマッピングを比較する格子の間隔 [px]
+const MAPPING_GRID_STEP: i32 = 8;
+
+/// 比較に使う内部パラメータの名前 (std_intrinsics と同じ順序)
+const PARAMETER_NAMES: [&str; 18] = [
+    "fx", "fy", "cx", "cy", "k1", "k2", "p1", "p2", "k3", "k4", "k5", "k6", "s1", "s2", "s3", "s4", "τx", "τy",
+];
+
+/// 2つのキャリブレーションの比較結果
+#[derive(Serialize, Debug)]
+pub struct CalibrationComparison {
+    pub parameters: Vec<ParameterDelta>,
+    /// 2つの歪み補正のマッピングで、同じ補正後の画素が参照する元画像の位置の差の最大値 [px]
+    pub max_displacement: f64,
+    /// 差が最大になる補正後の画素の位置
+    pub max_displacement_at: (f64, f64),
+    pub max_displacement_threshold: f64,
+    pub max_sigma_threshold: f64,
+    pub passed: bool,
+    /// 不合格の理由
+    pub failures: Vec<String>,
+}
+
+/// 1つのパラメータの差
+#[derive(Serialize, Debug)]
+pub struct ParameterDelta {
+    pub name: String,
+    pub value_a: f64,
+    pub value_b: f64,
+    pub delta: f64,
+    /// 2つの標準偏差を合成した値 (どちらかに標準偏差が無い場合は None)
+    pub sigma: Option<f64>,
+    /// 差が標準偏差の何倍か
+    pub delta_sigma: Option<f64>,
+}
+
+impl CalibrationComparison {
+    /// 2つのキャリブレーションを比較する
+    ///
+    /// 差が標準偏差の `max_sigma` 倍を超えるパラメータがあるか、
+    /// 歪み補正のマッピングの差が `max_displacement` [px] を超える場合は不合格とする
+    pub fn compare(a: &CalibrationResult, b: &CalibrationResult, max_displacement: f64, max_sigma: f64) -> Result<Self> {
+        if a.image_size != b.image_size {
+            return Err(CalibrationError::Config(format!(
+                "the image sizes differ ({}x{} and {}x{})",
+                a.image_size.width, a.image_size.height, b.image_size.width, b.image_size.height
+            )));
+        }
+
+        let parameters = parameter_deltas(a, b);
+        let (displacement, displacement_at) = max_mapping_displacement(a, b)?;
+
+        let mut failures = Vec::new();
+        for parameter in &parameters {
+            if let Some(delta_sigma) = parameter.delta_sigma.filter(|delta_sigma| *delta_sigma > max_sigma) {
+                failures.push(format!(
+                    "{} changed by {:.2} sigma (max {:.2})",
+                    parameter.name, delta_sigma, max_sigma
+                ));
+            }
+        }
+        if displacement > max_displacement {
+            failures.push(format!(
+                "undistortion mapping moved by {:.3} px (max {:.3})",
+                displacement, max_displacement
+            ));
+        }
+
+        Ok(Self {
+            parameters,
+            max_displacement: displacement,
+            max_displacement_at: displacement_at,
+            max_displacement_threshold: max_displacement,
+            max_sigma_threshold: max_sigma,
+            passed: failures.is_empty(),
+            failures,
+        })
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:<6} {:>14} {:>14} {:>14} {:>12} {:>8}",
+            "", "A", "B", "delta", "sigma", "d/sigma"
+        );
+        for parameter in &self.parameters {
+            let sigma = parameter.sigma.map_or("-".to_string(), |sigma| format!("{:.6}", sigma));
+            let delta_sigma = parameter.delta_sigma.map_or("-".to_string(), |delta_sigma| format!("{:.2}", delta_sigma));
+            println!(
+                "{:<6} {:>14.6} {:>14.6} {:>14.6} {:>12} {:>8}",
+                parameter.name, parameter.value_a, parameter.value_b, parameter.delta, sigma, delta_sigma
+            );
+        }
+        println!(
+            "Max undistortion displacement: {:.3} px at ({:.0}, {:.0})",
+            self.max_displacement, self.max_displacement_at.0, self.max_displacement_at.1
+        );
+
+        if self.passed {
+            println!("Result: PASS");
+        } else {
+            println!("Result: FAIL");
+            for failure in &self.failures {
+                println!("  - {}", failure);
+            }
+        }
+    }
+}
+
+/// 内部パラメータと歪み係数の差
+/// 歪み係数の数が異なる場合、無い係数は 0 として比べる
+fn parameter_deltas(a: &CalibrationResult, b: &CalibrationResult) -> Vec<ParameterDelta> {
+    let values = |calibration: &CalibrationResult| {
+        let intrinsics = &calibration.intrinsics;
+        let mut values = vec![intrinsics.fx, intrinsics.fy, intrinsics.cx, intrinsics.cy];
+        values.extend_from_slice(calibration.distortion.coefficients());
+        values
+    };
+    let values_a = values(a);
+    let values_b = values(b);
+    let count = values_a.len().max(values_b.len()).min(PARAMETER_NAMES.len());
+
+    (0..count)
+        .map(|i| {
+            let value_a = values_a.get(i).copied().unwrap_or(0.0);
+            let value_b = values_b.get(i).copied().unwrap_or(0.0);
+            let delta = value_b - value_a;
+            let sigma = match (a.metadata.std_intrinsics.get(i), b.metadata.std_intrinsics.get(i)) {
+                (Some(sigma_a), Some(sigma_b)) => Some(sigma_a.hypot(*sigma_b)),
+                _ => None,
+            };
+            ParameterDelta {
+                name: PARAMETER_NAMES[i].to_string(),
+                value_a,
+                value_b,
+                delta,
+                sigma,
+                delta_sigma: sigma.filter(|sigma| *sigma > 0.0).map(|sigma| delta.abs() / sigma),
+            }
+        })
+        .collect()
+}
+
+/// 2つの歪み補正のマッピング (init_undistort_rectify_map と同じく、補正後の画素から元画像の画素への対応) の差の最大値
+///
+/// 補正後の画素を各キャリブレーションのカメラ行列で正規化座標に戻し、歪みをかけて元画像に投影する
+fn max_mapping_displacement(a: &CalibrationResult, b: &CalibrationResult) -> Result<(f64, (f64, f64))> {
+    let size = a.image_size.to_size();
+    let mut pixels = Vec::new();
+    for y in (0..=size.height).step_by(MAPPING_GRID_STEP as usize) {
+        for x in (0..=size.width).step_by(MAPPING_GRID_STEP as usize) {
+            pixels.push((x as f64, y as f64));
+        }
+    }
+
+    let map_a = source_pixels(a, &pixels)?;
+    let map_b = source_pixels(b, &pixels)?;
+
+    let mut max_displacement = 0.0;
+    let mut max_displacement_at = (0.0, 0.0);
+    for ((pixel, source_a), source_b) in pixels.iter().zip(map_a.iter()).zip(map_b.iter()) {
+        let displacement = ((source_a.x - source_b.x) as f64).hypot((source_a.y - source_b.y) as f64);
+        if displacement > max_displacement {
+            max_displacement = displacement;
+            max_displacement_at = *pixel;
+        }
+    }
+    Ok((max_displacement, max_displacement_at))
+}
+
+/// 補正後の画素が参照する元画像の位置
+fn source_pixels(calibration: &CalibrationResult, pixels: &[(f64, f64)]) -> Result<Vector<Point2f>> {
+    let intrinsics = &calibration.intrinsics;
+    let rays: Vector<Point3f> = pixels
+        .iter()
+        .map(|(u, v)| {
+            let y = (v - intrinsics.cy) / intrinsics.fy;
+            let x = (u - intrinsics.cx - intrinsics.skew * y) / intrinsics.fx;
+            Point3f::new(x as f32, y as f32, 1.0)
+        })
+        .collect();
+
+    let zero = Mat::from_slice(&[0.0f64; 3])?.try_clone()?;
+    let mut projected = Vector::<Point2f>::new();
+    calib3d::project_points(
+        &rays,
+        &zero,
+        &zero,
+        &intrinsics.to_mat()?,
+        &calibration.distortion.to_mat()?,
+        &mut projected,
+        &mut Mat::default(),
+        0.0,
+    )?;
+    Ok(projected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera_model::{Distortion, ImageSize, Intrinsics, Metadata, SCHEMA_VERSION};
+
+    fn calibration(distortion: Distortion, std_intrinsics: Vec<f64>) -> CalibrationResult {
+        CalibrationResult {
+            schema_version: SCHEMA_VERSION,
+            image_size: ImageSize { width: 640, height: 480 },
+            intrinsics: Intrinsics { fx: 500.0, fy: 500.0, cx: 320.0, cy: 240.0, skew: 0.0 },
+            distortion,
+            poses: Vec::new(),
+            metadata: Metadata {
+                rms: Some(0.2),
+                total_error: 0.2,
+                reprojection: None,
+                std_intrinsics,
+                pattern: None,
+                units: None,
+                created_at: None,
+                tool: String::new(),
+                migrated_from: None,
+            },
+        }
+    }
+
+    #[test]
+    fn identical_calibrations_have_no_displacement_and_pass() {
+        let a = calibration(Distortion::BrownConrady([0.1, -0.2, 0.001, 0.002, 0.05]), vec![1.0; 9]);
+
+        let comparison = CalibrationComparison::compare(&a, &a.clone(), 0.0, 0.0).unwrap();
+
+        assert_eq!(comparison.max_displacement, 0.0);
+        assert!(comparison.parameters.iter().all(|parameter| parameter.delta == 0.0));
+        assert!(comparison.passed, "{:?}", comparison.failures);
+    }
+
+    #[test]
+    fn sigma_combines_the_standard_deviations_of_both_calibrations() {
+        let a = calibration(Distortion::None, vec![3.0, 0.0, 1.0, 1.0]);
+        let mut b = calibration(Distortion::None, vec![4.0, 0.0, 1.0, 1.0]);
+        b.intrinsics.fx += 10.0;
+
+        let parameters = parameter_deltas(&a, &b);
+
+        assert_eq!(parameters[0].name, "fx");
+        assert_eq!(parameters[0].delta, 10.0);
+        assert_eq!(parameters[0].sigma, Some(5.0));
+        assert_eq!(parameters[0].delta_sigma, Some(2.0));
+        // 標準偏差が 0 の場合は倍率を求めない
+        assert_eq!(parameters[1].sigma, Some(0.0));
+        assert_eq!(parameters[1].delta_sigma, None);
+    }
+
+    #[test]
+    fn a_change_beyond_max_sigma_fails() {
+        let a = calibration(Distortion::None, vec![3.0, 1.0, 1.0, 1.0]);
+        let mut b = calibration(Distortion::None, vec![4.0, 1.0, 1.0, 1.0]);
+        b.intrinsics.fx += 20.0;
+
+        let comparison = CalibrationComparison::compare(&a, &b, f64::INFINITY, 3.0).unwrap();
+
+        assert!(!comparison.passed);
+        assert_eq!(comparison.failures.len(), 1);
+        assert!(comparison.failures[0].starts_with("fx changed by 4.00 sigma"), "{}", comparison.failures[0]);
+    }
+
+    #[test]
+    fn missing_standard_deviations_give_no_sigma_and_no_failure() {
+        let a = calibration(Distortion::None, Vec::new());
+        let mut b = calibration(Distortion::None, vec![1.0; 4]);
+        b.intrinsics.fx += 100.0;
+
+        let comparison = CalibrationComparison::compare(&a, &b, f64::INFINITY, 1.0).unwrap();
+
+        assert!(comparison.parameters.iter().all(|parameter| parameter.sigma.is_none() && parameter.delta_sigma.is_none()));
+        assert!(comparison.passed, "{:?}", comparison.failures);
+    }
+
+    #[test]
+    fn missing_coefficients_are_compared_as_zero() {
+        let a = calibration(Distortion::BrownConrady([0.1, -0.2, 0.0, 0.0, 0.05]), Vec::new());
+        let b = calibration(Distortion::Rational([0.1, -0.2, 0.0, 0.0, 0.05, 0.0, 0.0, 0.0]), Vec::new());
+
+        let comparison = CalibrationComparison::compare(&a, &b, 1e-6, 1.0).unwrap();
+
+        let names: Vec<&str> = comparison.parameters.iter().map(|parameter| parameter.name.as_str()).collect();
+        assert_eq!(names, ["fx", "fy", "cx", "cy", "k1", "k2", "p1", "p2", "k3", "k4", "k5", "k6"]);
+        assert!(comparison.parameters[9..].iter().all(|parameter| parameter.value_a == 0.0 && parameter.delta == 0.0));
+        // 追加の係数が 0 なので同じマッピングになる
+        assert!(comparison.max_displacement < 1e-3, "{}", comparison.max_displacement);
+        assert!(comparison.passed, "{:?}", comparison.failures);
+    }
+
+    #[test]
+    fn a_different_distortion_moves_the_mapping() {
+        let a = calibration(Distortion::BrownConrady([0.0; 5]), Vec::new());
+        let b = calibration(Distortion::BrownConrady([0.1, 0.0, 0.0, 0.0, 0.0]), Vec::new());
+
+        let comparison = CalibrationComparison::compare(&a, &b, 0.5, 1.0).unwrap();
+
+        // 画像の隅 (r = 0.8) で 0.1 * r^2 * 400 px = 25.6 px
+        assert!((comparison.max_displacement - 25.6).abs() < 0.01, "{}", comparison.max_displacement);
+        let (x, y) = comparison.max_displacement_at;
+        assert!([0.0, 640.0].contains(&x) && [0.0, 480.0].contains(&y), "({}, {})", x, y);
+        assert!(!comparison.passed);
+    }
+
+    #[test]
+    fn different_image_sizes_are_rejected() {
+        let a = calibration(Distortion::None, Vec::new());
+        let mut b = a.clone();
+        b.image_size = ImageSize { width: 1280, height: 960 };
+
+        assert!(matches!(CalibrationComparison::compare(&a, &b, 1.0, 1.0), Err(CalibrationError::Config(_))));
+    }
+}
@@ -0,0 +1,633 @@
+use std::fs;
+use std::path::PathBuf;
+
+use opencv::{
+    calib3d,
+    core::{TermCriteria, TermCriteria_Type},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::command_line::{CalibrationPattern, ChessboardDetector, RunArgs};
+use crate::error::{CalibrationError, Result};
+use crate::file::CustomFile;
+use crate::target::TargetDescription;
+
+/// キャリブレーション実行の設定 (TOML)
+///
+/// 設定ファイルで指定されなかった値はパターンごとの既定値で補われる
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Config {
+    pub pattern: CalibrationPattern,
+    pub dataset: DatasetConfig,
+    pub video: VideoConfig,
+    pub detection: DetectionConfig,
+    pub checks: CheckConfig,
+    pub calibration: CalibrationConfig,
+    pub preview: PreviewConfig,
+    pub output: OutputConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DatasetConfig {
+    /// キャリブレーション画像のディレクトリまたはグロブパターン
+    pub images: String,
+    /// 読み込む画像の拡張子 (大文字小文字を区別しない)
+    pub extensions: Vec<String>,
+    /// サブディレクトリの画像も読み込む
+    pub recursive: bool,
+    /// ターゲット記述ファイル
+    pub target: String,
+    /// 歪み補正の結果を確認する画像
+    pub undistort_image: String,
+}
+
+/// 動画入力の設定
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VideoConfig {
+    /// 動画ファイル。指定した場合は `dataset.images` の代わりに動画のフレームを使う
+    pub path: Option<String>,
+    /// 何フレームごとに取り出すか
+    pub frame_step: usize,
+    /// これより鮮明度 (ラプラシアンの分散) が小さいフレームは除外する
+    pub min_sharpness: f64,
+    /// 直前に選んだフレームとの平均輝度差がこれより小さいフレームは除外する
+    pub min_novelty: f64,
+    /// 選ぶフレームの上限
+    pub max_frames: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DetectionConfig {
+    pub detector: ChessboardDetector,
+    pub sb_exhaustive: bool,
+    pub sb_accuracy: bool,
+    pub sb_larger: bool,
+    pub sb_marker: bool,
+    /// マーカー付きボードの部分検出
+    pub partial: bool,
+    /// 部分検出で受け付ける最小のコーナー数 (cols, rows)
+    pub partial_min_size: (i32, i32),
+    /// AprilGrid で1枚の画像に必要なタグ数
+    pub min_tag_count: usize,
+    /// 検出結果をキャッシュし、変更の無い画像の検出を省略する
+    pub cache: bool,
+    pub corner_sub_pix: CornerSubPixConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CornerSubPixConfig {
+    pub window_width: i32,
+    pub window_height: i32,
+    pub zero_zone: i32,
+    /// corner_sub_pix の終了条件
+    pub criteria: CriteriaConfig,
+}
+
+/// キャリブレーション前のビューのチェック
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CheckConfig {
+    pub enabled: bool,
+    /// 警告もエラーとして扱う
+    pub strict: bool,
+    /// 必要なビューの数 (下回る場合は常にエラー)
+    pub min_views: usize,
+    /// 被覆を数える画像の格子 (cols, rows)
+    pub coverage_grid: (i32, i32),
+    /// コーナーが含まれる格子のセルの割合の下限 (0 - 1)
+    pub min_coverage: f64,
+    /// ボードの傾きの範囲 (最大 - 最小) の下限 [deg]
+    pub min_tilt_range_deg: f64,
+    /// 最も遠いビューと最も近いビューの距離の比の下限
+    pub min_distance_ratio: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CalibrationConfig {
+    pub model: ModelConfig,
+    /// calibrate_camera の最適化 (Levenberg-Marquardt) の終了条件
+    pub criteria: CriteriaConfig,
+}
+
+/// calibrate_camera に渡すモデルのフラグ
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ModelConfig {
+    pub fix_principal_point: bool,
+    pub fix_aspect_ratio: bool,
+    pub zero_tangent_dist: bool,
+    pub fix_k1: bool,
+    pub fix_k2: bool,
+    pub fix_k3: bool,
+    pub rational_model: bool,
+    pub thin_prism_model: bool,
+    pub tilted_model: bool,
+}
+
+/// 反復の終了条件 (どちらかを満たすと終了する)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CriteriaConfig {
+    /// 反復回数の上限
+    pub max_count: i32,
+    /// 1回の反復での変化量がこれより小さくなったら終了する
+    pub eps: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PreviewConfig {
+    /// 検出結果をウィンドウに表示する
+    pub enabled: bool,
+    pub window_title: String,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub wait_key_delay: i32,
+    pub text_point: (i32, i32),
+    pub text_font_scale: f64,
+    pub text_color: (f64, f64, f64, f64),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OutputConfig {
+    /// 出力ディレクトリ
+    pub directory: String,
+    /// ビューごとの再投影残差の画像を出力する
+    pub residual_images: bool,
+    /// 残差の画像で残差ベクトルを拡大する倍率
+    pub residual_scale: f64,
+    /// 画像を埋め込んだ HTML レポートを出力する
+    pub html_report: bool,
+}
+
+impl Config {
+    /// パターンごとの既定値
+    pub fn default_for(pattern: CalibrationPattern) -> Self {
+        let name = match pattern {
+            CalibrationPattern::ChessBoard => "chessboard",
+            CalibrationPattern::SymmetricCircleGrid | CalibrationPattern::AsymmetricCircleGrid => "circle_grid",
+            CalibrationPattern::ChArUco => "charuco",
+            CalibrationPattern::AprilGrid => "aprilgrid",
+        };
+
+        Self {
+            pattern,
+            dataset: DatasetConfig {
+                images: format!("./img/{}_dataset", name),
+                extensions: CustomFile::default_image_extensions(),
+                recursive: false,
+                target: format!("./img/{}_dataset/target.json", name),
+                undistort_image: format!("./img/{}_dataset/calib04.jpeg", name),
+            },
+            video: VideoConfig {
+                path: None,
+                frame_step: 10,
+                min_sharpness: 100.0,
+                min_novelty: 2.0,
+                max_frames: None,
+            },
+            detection: DetectionConfig {
+                detector: ChessboardDetector::Classic,
+                sb_exhaustive: false,
+                sb_accuracy: false,
+                sb_larger: false,
+                sb_marker: false,
+                partial: false,
+                partial_min_size: (4, 3),
+                min_tag_count: 4,
+                cache: true,
+                corner_sub_pix: CornerSubPixConfig {
+                    window_width: 11,
+                    window_height: 11,
+                    zero_zone: -1,
+                    criteria: CriteriaConfig {
+                        max_count: 30,
+                        eps: 0.001,
+                    },
+                },
+            },
+            checks: CheckConfig {
+                enabled: true,
+                strict: false,
+                min_views: 10,
+                coverage_grid: (4, 3),
+                min_coverage: 0.75,
+                min_tilt_range_deg: 20.0,
+                min_distance_ratio: 1.3,
+            },
+            calibration: CalibrationConfig {
+                model: ModelConfig::default(),
+                criteria: CriteriaConfig {
+                    max_count: 30,
+                    eps: 0.001,
+                },
+            },
+            preview: PreviewConfig {
+                enabled: true,
+                window_title: "Chessboard Corners Detection".to_string(),
+                window_width: 900,
+                window_height: 700,
+                wait_key_delay: 1000,
+                text_point: (10, 100),
+                text_font_scale: 3.0,
+                text_color: (0.0, 255.0, 0.0, 0.0), // GREEN
+            },
+            output: OutputConfig {
+                directory: format!("./out/{}", name),
+                residual_images: true,
+                residual_scale: 20.0,
+                html_report: true,
+            },
+        }
+    }
+
+    /// 設定ファイルとコマンドライン引数から実行時の設定を組み立てる
+    /// 優先順位: コマンドライン引数 > 設定ファイル > 既定値
+    pub fn load(args: &RunArgs) -> Result<Self> {
+        let file_table = match &args.config {
+            Some(config_path) => {
+                let text = fs::read_to_string(config_path).map_err(|e| CalibrationError::io(config_path, e))?;
+                text.parse::<toml::Table>()
+                    .map_err(|e| CalibrationError::serialization(config_path, e))?
+            }
+            None => toml::Table::new(),
+        };
+
+        let pattern = match args.pattern {
+            Some(pattern) => pattern,
+            None => match file_table.get("pattern") {
+                Some(value) => value
+                    .clone()
+                    .try_into()
+                    .map_err(|e| CalibrationError::Config(format!("invalid pattern in the config file: {}", e)))?,
+                None => {
+                    return Err(CalibrationError::Config(
+                        "no calibration pattern given. Use --pattern or set `pattern` in the config file.".to_string(),
+                    ))
+                }
+            },
+        };
+
+        // 既定値の上に設定ファイルの値を重ねる
+        let mut table = match toml::Value::try_from(Self::default_for(pattern)) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => return Err(CalibrationError::Config("failed to build the default config".to_string())),
+        };
+        merge_table(&mut table, file_table);
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| CalibrationError::Config(e.to_string()))?;
+        config.pattern = pattern;
+
+        config.apply_args(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 値の範囲を検証する
+    /// 0 や負の格子・ウィンドウサイズは検出やチェックで不正なメモリ確保や NaN の原因になるため、実行前にエラーにする
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(CalibrationError::Config(message));
+
+        let corner_sub_pix = &self.detection.corner_sub_pix;
+        if corner_sub_pix.window_width <= 0 || corner_sub_pix.window_height <= 0 {
+            return invalid(format!(
+                "detection.corner_sub_pix window size must be positive, got {}x{}",
+                corner_sub_pix.window_width, corner_sub_pix.window_height
+            ));
+        }
+        if corner_sub_pix.zero_zone < -1 {
+            return invalid(format!(
+                "detection.corner_sub_pix.zero_zone must be -1 (disabled) or non-negative, got {}",
+                corner_sub_pix.zero_zone
+            ));
+        }
+        corner_sub_pix.criteria.validate("detection.corner_sub_pix.criteria")?;
+        self.calibration.criteria.validate("calibration.criteria")?;
+
+        let (min_cols, min_rows) = self.detection.partial_min_size;
+        if min_cols <= 0 || min_rows <= 0 {
+            return invalid(format!(
+                "detection.partial_min_size must be positive, got ({}, {})",
+                min_cols, min_rows
+            ));
+        }
+        if self.detection.min_tag_count == 0 {
+            return invalid("detection.min_tag_count must be at least 1".to_string());
+        }
+
+        let (cols, rows) = self.checks.coverage_grid;
+        if cols <= 0 || rows <= 0 {
+            return invalid(format!("checks.coverage_grid must be positive, got ({}, {})", cols, rows));
+        }
+        if !(0.0..=1.0).contains(&self.checks.min_coverage) {
+            return invalid(format!(
+                "checks.min_coverage must be between 0 and 1, got {}",
+                self.checks.min_coverage
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// コマンドライン引数で設定を上書きする
+    fn apply_args(&mut self, args: &RunArgs) {
+        if let Some(images) = &args.dataset {
+            self.dataset.images = images.clone();
+        }
+        if let Some(target) = &args.target {
+            self.dataset.target = target.clone();
+        }
+        if let Some(extensions) = &args.extensions {
+            self.dataset.extensions = extensions.clone();
+        }
+        self.dataset.recursive |= args.recursive;
+        if let Some(video) = &args.video {
+            self.video.path = Some(video.clone());
+        }
+        if let Some(frame_step) = args.frame_step {
+            self.video.frame_step = frame_step;
+        }
+        if let Some(directory) = &args.output_dir {
+            self.output.directory = directory.clone();
+        }
+        if let Some(detector) = args.detector {
+            self.detection.detector = detector;
+        }
+        self.detection.sb_exhaustive |= args.sb_exhaustive;
+        self.detection.sb_accuracy |= args.sb_accuracy;
+        self.detection.sb_larger |= args.sb_larger;
+        self.detection.sb_marker |= args.sb_marker;
+        self.detection.partial |= args.partial;
+        if args.no_cache {
+            self.detection.cache = false;
+        }
+        if args.skip_checks {
+            self.checks.enabled = false;
+        }
+        self.checks.strict |= args.strict_checks;
+        if args.no_preview {
+            self.preview.enabled = false;
+        }
+    }
+
+    /// 検出結果に影響する設定 (検出キャッシュの有効性の判定に使う)
+    pub fn detection_settings(&self, target: &TargetDescription) -> serde_json::Value {
+        let mut detection = serde_json::to_value(&self.detection).unwrap_or_default();
+        if let Some(detection) = detection.as_object_mut() {
+            detection.remove("cache");
+        }
+
+        serde_json::json!({
+            "pattern": self.pattern,
+            "target": target,
+            "detection": detection,
+        })
+    }
+
+    /// 出力ディレクトリ内のファイルパス
+    pub fn output_path(&self, file_name: &str) -> String {
+        PathBuf::from(&self.output.directory)
+            .join(file_name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// 実際に使用した設定を出力ディレクトリに書き出す
+    pub fn save_effective(&self, file_path: &str) -> Result<()> {
+        let text = toml::to_string_pretty(self).map_err(|e| CalibrationError::serialization(file_path, e))?;
+        fs::write(file_path, text).map_err(|e| CalibrationError::io(file_path, e))?;
+        println!("Effective config written to: {}", file_path);
+        Ok(())
+    }
+}
+
+impl ModelConfig {
+    /// calibrate_camera のフラグに変換する
+    pub fn to_flags(&self) -> i32 {
+        [
+            (self.fix_principal_point, calib3d::CALIB_FIX_PRINCIPAL_POINT),
+            (self.fix_aspect_ratio, calib3d::CALIB_FIX_ASPECT_RATIO),
+            (self.zero_tangent_dist, calib3d::CALIB_ZERO_TANGENT_DIST),
+            (self.fix_k1, calib3d::CALIB_FIX_K1),
+            (self.fix_k2, calib3d::CALIB_FIX_K2),
+            (self.fix_k3, calib3d::CALIB_FIX_K3),
+            (self.rational_model, calib3d::CALIB_RATIONAL_MODEL),
+            (self.thin_prism_model, calib3d::CALIB_THIN_PRISM_MODEL),
+            (self.tilted_model, calib3d::CALIB_TILTED_MODEL),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+}
+
+impl CriteriaConfig {
+    /// `name` はエラーメッセージに使う設定のキー
+    fn validate(&self, name: &str) -> Result<()> {
+        if self.max_count <= 0 {
+            return Err(CalibrationError::Config(format!("{}.max_count must be positive, got {}", name, self.max_count)));
+        }
+        if !(self.eps.is_finite() && self.eps > 0.0) {
+            return Err(CalibrationError::Config(format!("{}.eps must be positive, got {}", name, self.eps)));
+        }
+        Ok(())
+    }
+
+    pub fn to_term_criteria(&self) -> Result<TermCriteria> {
+        Ok(TermCriteria::new(
+            (TermCriteria_Type::COUNT as i32) + (TermCriteria_Type::EPS as i32),
+            self.max_count,
+            self.eps,
+        )?)
+    }
+}
+
+/// `overlay` の値で `base` を再帰的に上書きする
+fn merge_table(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_table(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::command_line::{Args, Command};
+
+    fn run_args(args: &[&str]) -> RunArgs {
+        let args = Args::try_parse_from(["camera_calibrate", "calibrate"].iter().chain(args)).unwrap();
+        match args.command {
+            Command::Calibrate(run_args) => run_args,
+            command => panic!("unexpected command: {:?}", command),
+        }
+    }
+
+    /// テストごとに別の一時ファイルに設定を書き出す
+    fn config_file(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("camera_calibrate_{}_{}.toml", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn table(text: &str) -> toml::Table {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn merge_table_overrides_nested_values_and_keeps_the_rest() {
+        let mut base = table(
+            r#"
+            pattern = "chessboard"
+            [detection]
+            partial = false
+            min_tag_count = 4
+            [detection.corner_sub_pix]
+            window_width = 11
+            window_height = 11
+            "#,
+        );
+        merge_table(
+            &mut base,
+            table(
+                r#"
+                [detection]
+                partial = true
+                [detection.corner_sub_pix]
+                window_width = 5
+                "#,
+            ),
+        );
+
+        assert_eq!(
+            base,
+            table(
+                r#"
+                pattern = "chessboard"
+                [detection]
+                partial = true
+                min_tag_count = 4
+                [detection.corner_sub_pix]
+                window_width = 5
+                window_height = 11
+                "#,
+            )
+        );
+    }
+
+    #[test]
+    fn merge_table_replaces_arrays_and_adds_new_keys() {
+        let mut base = table(
+            r#"
+            [dataset]
+            extensions = ["jpeg", "png"]
+            "#,
+        );
+        merge_table(
+            &mut base,
+            table(
+                r#"
+                [dataset]
+                extensions = ["tif"]
+                [video]
+                path = "calib.mp4"
+                "#,
+            ),
+        );
+
+        assert_eq!(
+            base,
+            table(
+                r#"
+                [dataset]
+                extensions = ["tif"]
+                [video]
+                path = "calib.mp4"
+                "#,
+            )
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_the_file_and_the_file_overrides_the_defaults() {
+        let path = config_file(
+            "precedence",
+            r#"
+            pattern = "chessboard"
+            [dataset]
+            images = "./from_file"
+            target = "./from_file/target.json"
+            [detection]
+            detector = "sb"
+            "#,
+        );
+
+        let config = Config::load(&run_args(&["--config", &path, "--dataset", "./from_cli", "--detector", "both"])).unwrap();
+        fs::remove_file(&path).unwrap();
+        let defaults = Config::default_for(CalibrationPattern::ChessBoard);
+
+        // コマンドライン引数 > 設定ファイル
+        assert_eq!(config.dataset.images, "./from_cli");
+        assert_eq!(config.detection.detector, ChessboardDetector::Both);
+        // 設定ファイル > 既定値
+        assert_eq!(config.dataset.target, "./from_file/target.json");
+        // 既定値
+        assert_eq!(config.output.directory, defaults.output.directory);
+        assert_eq!(config.checks.coverage_grid, defaults.checks.coverage_grid);
+    }
+
+    #[test]
+    fn command_line_pattern_selects_the_defaults() {
+        let path = config_file("pattern", "pattern = \"chessboard\"\n");
+
+        let config = Config::load(&run_args(&["--config", &path, "--pattern", "aprilgrid"])).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.pattern, CalibrationPattern::AprilGrid);
+        assert_eq!(config.output.directory, "./out/aprilgrid");
+    }
+
+    #[test]
+    fn load_rejects_invalid_values_from_the_file() {
+        let cases = [
+            ("coverage_grid", "[checks]\ncoverage_grid = [0, 3]\n"),
+            ("negative_coverage_grid", "[checks]\ncoverage_grid = [4, -1]\n"),
+            ("min_coverage", "[checks]\nmin_coverage = 1.5\n"),
+            ("max_count", "[calibration.criteria]\nmax_count = 0\n"),
+            ("eps", "[detection.corner_sub_pix.criteria]\neps = -0.1\n"),
+            ("window", "[detection.corner_sub_pix]\nwindow_width = 0\n"),
+            ("zero_zone", "[detection.corner_sub_pix]\nzero_zone = -2\n"),
+            ("partial_min_size", "[detection]\npartial_min_size = [0, 3]\n"),
+            ("min_tag_count", "[detection]\nmin_tag_count = 0\n"),
+        ];
+
+        for (name, text) in cases {
+            let path = config_file(name, &format!("pattern = \"chessboard\"\n{}", text));
+            let result = Config::load(&run_args(&["--config", &path]));
+            fs::remove_file(&path).unwrap();
+            assert!(matches!(result, Err(CalibrationError::Config(_))), "{}: {:?}", name, result.err());
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid_for_every_pattern() {
+        for pattern in [
+            CalibrationPattern::ChessBoard,
+            CalibrationPattern::SymmetricCircleGrid,
+            CalibrationPattern::AsymmetricCircleGrid,
+            CalibrationPattern::ChArUco,
+            CalibrationPattern::AprilGrid,
+        ] {
+            Config::default_for(pattern).validate().unwrap();
+        }
+    }
+}
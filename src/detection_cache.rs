@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::camera_calibration::{Detection, DetectorReport, ImageReport};
+use crate::error::{CalibrationError, Result};
+use crate::file::CustomFile;
+
+/// 画像ごとの検出結果のキャッシュ
+///
+/// 検出設定 (`settings`) が同じで、画像のハッシュが変わっていなければ検出を省略できる
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DetectionCache {
+    /// キャッシュファイルの形式。`FORMAT_VERSION` と異なるキャッシュは使わない
+    #[serde(default)]
+    pub format_version: u32,
+    /// 検出結果に影響するパターン・ターゲット・検出設定
+    pub settings: serde_json::Value,
+    pub entries: Vec<CacheEntry>,
+}
+
+/// 検出が必要な画像とそのファイルのハッシュ
+pub type PendingImage = (PathBuf, String);
+
+/// キャッシュされた1枚の画像の検出結果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheEntry {
+    pub image_path: String,
+    /// 画像ファイルの SHA-256
+    pub file_hash: String,
+    /// 画像の分類
+    pub report: ImageReport,
+    /// パターンが検出されなかった場合は None
+    pub detection: Option<Detection>,
+    /// チェスボードの検出器ごとの結果。他のパターンでは None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detector: Option<DetectorReport>,
+}
+
+impl DetectionCache {
+    /// 現在のキャッシュファイルの形式 (2: 検出器ごとの結果を追加)
+    pub const FORMAT_VERSION: u32 = 2;
+
+    /// 空のキャッシュを作成する
+    pub fn new(settings: &serde_json::Value) -> Self {
+        Self {
+            format_version: Self::FORMAT_VERSION,
+            settings: settings.clone(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// キャッシュファイルを読み込む
+    /// ファイルが無い場合や形式・検出設定が異なる場合は空のキャッシュを返す
+    pub fn load(file_path: &str, settings: &serde_json::Value) -> Self {
+        let empty = Self::new(settings);
+
+        let Ok(json) = fs::read_to_string(file_path) else {
+            return empty;
+        };
+        match serde_json::from_str::<Self>(&json) {
+            Ok(cache) if cache.format_version != Self::FORMAT_VERSION => {
+                println!("Detection cache format changed. Ignoring the cache: {}", file_path);
+                empty
+            }
+            Ok(cache) if cache.settings == *settings => {
+                println!("Loaded {} cached detections from: {}", cache.entries.len(), file_path);
+                cache
+            }
+            Ok(_) => {
+                println!("Detection settings changed. Ignoring the cache: {}", file_path);
+                empty
+            }
+            Err(e) => {
+                eprintln!("Invalid detection cache '{}': {}", file_path, e);
+                empty
+            }
+        }
+    }
+
+    /// 画像パスとハッシュが一致するエントリを探す
+    pub fn find(&self, image_path: &str, file_hash: &str) -> Option<&CacheEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.image_path == image_path && entry.file_hash == file_hash)
+    }
+
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        CustomFile::write_json(file_path, self)
+    }
+
+    /// 画像ファイルの SHA-256 を16進文字列で返す
+    pub fn file_hash(image_path: &Path) -> Result<String> {
+        let bytes = fs::read(image_path).map_err(|e| CalibrationError::io(image_path.to_string_lossy(), e))?;
+        let digest = Sha256::digest(&bytes);
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// キャッシュを使って画像を検出済みと未検出に振り分ける
+    /// 戻り値は (キャッシュから再利用したエントリ, 検出が必要な画像)
+    pub fn partition(&self, image_paths: &[PathBuf]) -> Result<(Vec<CacheEntry>, Vec<PendingImage>)> {
+        let mut cached = Vec::new();
+        let mut pending = Vec::new();
+
+        for image_path in image_paths {
+            let file_hash = Self::file_hash(image_path)?;
+            match self.find(&image_path.to_string_lossy(), &file_hash) {
+                Some(entry) => cached.push(entry.clone()),
+                None => pending.push((image_path.clone(), file_hash)),
+            }
+        }
+
+        Ok((cached, pending))
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+use std::io;
+
+/// このクレートのエラー
+#[derive(Debug)]
+pub enum CalibrationError {
+    /// ファイルやディレクトリの読み書きに失敗した
+    Io { path: String, source: io::Error },
+    /// 画像を読み込めなかった (壊れている、または対応していない形式)
+    ImageDecode { path: String },
+    /// パターンの検出に失敗した
+    Detection(String),
+    /// キャリブレーションや姿勢推定に失敗した
+    Calibration(String),
+    /// 2つのキャリブレーションの差が閾値を超えた
+    ComparisonFailed(String),
+    /// JSON / TOML の読み書きに失敗した
+    Serialization { path: String, message: String },
+    /// 設定やコマンドライン引数、ターゲット記述が不正
+    Config(String),
+    /// OpenCV の関数がエラーを返した
+    OpenCv(opencv::Error),
+}
+
+pub type Result<T> = std::result::Result<T, CalibrationError>;
+
+impl CalibrationError {
+    pub fn io(path: impl Into<String>, source: io::Error) -> Self {
+        CalibrationError::Io { path: path.into(), source }
+    }
+
+    pub fn serialization(path: impl Into<String>, message: impl fmt::Display) -> Self {
+        CalibrationError::Serialization { path: path.into(), message: message.to_string() }
+    }
+
+    /// プロセスの終了コード
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CalibrationError::Config(_) => 2,
+            CalibrationError::Io { .. } => 3,
+            CalibrationError::ImageDecode { .. } => 4,
+            CalibrationError::Serialization { .. } => 5,
+            CalibrationError::Detection(_) => 6,
+            CalibrationError::Calibration(_) => 7,
+            CalibrationError::ComparisonFailed(_) => 8,
+            CalibrationError::OpenCv(_) => 10,
+        }
+    }
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalibrationError::Io { path, source } => write!(f, "'{}': {}", path, source),
+            CalibrationError::ImageDecode { path } => {
+                write!(f, "Failed to decode image '{}' (the file is corrupt or its format is not supported)", path)
+            }
+            CalibrationError::Detection(message) => write!(f, "Detection failed: {}", message),
+            CalibrationError::Calibration(message) => write!(f, "Calibration failed: {}", message),
+            CalibrationError::ComparisonFailed(message) => write!(f, "Calibrations differ: {}", message),
+            CalibrationError::Serialization { path, message } => write!(f, "'{}': {}", path, message),
+            CalibrationError::Config(message) => write!(f, "Invalid configuration: {}", message),
+            CalibrationError::OpenCv(e) => write!(f, "OpenCV error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CalibrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CalibrationError::Io { source, .. } => Some(source),
+            CalibrationError::OpenCv(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<opencv::Error> for CalibrationError {
+    fn from(e: opencv::Error) -> Self {
+        CalibrationError::OpenCv(e)
+    }
+}
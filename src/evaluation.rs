@@ -0,0 +1,299 @@
+use opencv::core::{Mat, Vector};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_calibration::{CameraCalibration, CameraCalibrationTrait, Detection},
+    camera_model::{CalibrationResult, Pose},
+    error::{CalibrationError, Result},
+};
+
+/// 再投影誤差の統計 [px]
+///
+/// 点の数の異なるビューを正しく重み付けするため、`rms` は全点から求める
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReprojectionStatistics {
+    pub view_count: usize,
+    /// 投影できたビューの点の数
+    pub point_count: usize,
+    /// 姿勢推定または投影に失敗したビューの数 (誤差の統計には含めない)
+    pub failed_view_count: usize,
+    /// 全点の RMS
+    pub rms: f64,
+    /// ビューごとの RMS の平均・中央値・最大値
+    pub mean_view_rms: f64,
+    pub median_view_rms: f64,
+    pub max_view_rms: f64,
+    /// 1点の誤差の最大値
+    pub max_point_error: f64,
+    /// 以前の total_error と同じ値 (投影に失敗したビューを 0 としたビューごとの RMS の平均)
+    /// 過去の結果と比べるためだけに残している
+    pub legacy_total_error: f64,
+}
+
+/// 1ビューの評価結果
+#[derive(Serialize, Debug, Clone)]
+pub struct ViewEvaluation {
+    pub image_name: String,
+    /// 姿勢を求められなかった場合は None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pose: Option<Pose>,
+    pub point_count: usize,
+    /// 姿勢推定または投影に失敗した場合は None
+    pub rms: Option<f64>,
+    pub max_error: Option<f64>,
+}
+
+/// 保存済みのキャリブレーションを新しい画像セットで評価した結果
+#[derive(Serialize, Debug, Clone)]
+pub struct Evaluation {
+    /// 評価したキャリブレーションファイル
+    pub calibration: String,
+    pub statistics: ReprojectionStatistics,
+    pub views: Vec<ViewEvaluation>,
+}
+
+impl ReprojectionStatistics {
+    /// ビューごとの各点の誤差 (`reprojection_residuals` の結果) から求める
+    pub fn new(residuals: &[Option<Vec<f64>>]) -> Self {
+        let view_rms: Vec<f64> = residuals.iter().flatten().filter_map(|errors| rms(errors)).collect();
+        let all_errors: Vec<f64> = residuals.iter().flatten().flatten().copied().collect();
+
+        Self {
+            view_count: residuals.len(),
+            point_count: all_errors.len(),
+            failed_view_count: residuals.iter().filter(|errors| errors.is_none()).count(),
+            rms: rms(&all_errors).unwrap_or(0.0),
+            mean_view_rms: if view_rms.is_empty() { 0.0 } else { view_rms.iter().sum::<f64>() / view_rms.len() as f64 },
+            median_view_rms: median(&view_rms),
+            max_view_rms: view_rms.iter().copied().fold(0.0, f64::max),
+            max_point_error: all_errors.iter().copied().fold(0.0, f64::max),
+            legacy_total_error: if residuals.is_empty() {
+                0.0
+            } else {
+                view_rms.iter().sum::<f64>() / residuals.len() as f64
+            },
+        }
+    }
+
+    pub fn print(&self) {
+        println!("Views: {} ({} points)", self.view_count, self.point_count);
+        if self.failed_view_count > 0 {
+            eprintln!(
+                "Pose estimation or projection failed for {} of {} views. They are not included in the statistics",
+                self.failed_view_count, self.view_count
+            );
+        }
+        println!("RMS Error (all points): {:.4} px", self.rms);
+        println!(
+            "Per-view RMS: mean {:.4} px, median {:.4} px, max {:.4} px",
+            self.mean_view_rms, self.median_view_rms, self.max_view_rms
+        );
+        println!("Max point error: {:.4} px", self.max_point_error);
+        println!("Total Error (legacy): {:.4} px", self.legacy_total_error);
+    }
+}
+
+impl Evaluation {
+    /// 固定した内部パラメータで各ビューの姿勢を solvePnP で求め、再投影誤差を評価する
+    pub fn run(calibration: &CalibrationResult, calibration_path: &str, detections: &[Detection]) -> Result<Self> {
+        let (obj_points, img_points) = Detection::to_point_vectors(detections);
+        if obj_points.is_empty() {
+            return Err(CalibrationError::Detection(
+                "the pattern was not detected in any image, nothing to evaluate".to_string(),
+            ));
+        }
+
+        let image_size = calibration.image_size;
+        if let Some(detection) = detections
+            .iter()
+            .find(|detection| detection.image_size != (image_size.width, image_size.height))
+        {
+            return Err(CalibrationError::Config(format!(
+                "the size {}x{} of '{}' differs from the calibrated image size {}x{}",
+                detection.image_size.0, detection.image_size.1, detection.image_path, image_size.width, image_size.height
+            )));
+        }
+
+        let camera_matrix = calibration.intrinsics.to_mat()?;
+        let dist_coeffs = calibration.distortion.to_mat()?;
+        let poses = CameraCalibration::solve_poses(&obj_points, &img_points, &camera_matrix, &dist_coeffs)?;
+
+        // 姿勢を求められなかったビューは空の Mat を渡し、誤差を None とする
+        let mut rvecs = Vector::<Mat>::new();
+        let mut tvecs = Vector::<Mat>::new();
+        for pose in &poses {
+            let (rvec, tvec) = pose.clone().unwrap_or_default();
+            rvecs.push(rvec);
+            tvecs.push(tvec);
+        }
+        let mut residuals =
+            CameraCalibration::reprojection_residuals(&obj_points, &img_points, &rvecs, &tvecs, &camera_matrix, &dist_coeffs)?;
+        for (errors, pose) in residuals.iter_mut().zip(&poses) {
+            if pose.is_none() {
+                *errors = None;
+            }
+        }
+
+        let mut views = Vec::with_capacity(detections.len());
+        for ((detection, errors), solved) in detections.iter().zip(residuals.iter()).zip(&poses) {
+            let view_rms = errors.as_deref().and_then(rms);
+            let pose = match solved {
+                Some((rvec, tvec)) => {
+                    let mut pose = Pose::from_mats(&detection.file_name, rvec, tvec)?;
+                    pose.reprojection_error = view_rms;
+                    Some(pose)
+                }
+                None => None,
+            };
+            views.push(ViewEvaluation {
+                image_name: detection.file_name.clone(),
+                pose,
+                point_count: detection.image_points.len(),
+                rms: view_rms,
+                max_error: errors.as_ref().map(|errors| errors.iter().copied().fold(0.0, f64::max)),
+            });
+        }
+
+        Ok(Self {
+            calibration: calibration_path.to_string(),
+            statistics: ReprojectionStatistics::new(&residuals),
+            views,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("{:<32} {:>7} {:>10} {:>10}", "Image", "Points", "RMS", "Max");
+        for view in &self.views {
+            let format = |value: Option<f64>| value.map_or("failed".to_string(), |value| format!("{:.4}", value));
+            println!(
+                "{:<32} {:>7} {:>10} {:>10}",
+                view.image_name,
+                view.point_count,
+                format(view.rms),
+                format(view.max_error)
+            );
+        }
+        self.statistics.print();
+    }
+
+    /// 姿勢を Mat で返す (残差画像の描画用)
+    /// 姿勢を求められなかったビューは空の Mat
+    pub fn pose_mats(&self) -> Result<(Vector<Mat>, Vector<Mat>)> {
+        let mut rvecs = Vector::<Mat>::new();
+        let mut tvecs = Vector::<Mat>::new();
+        for view in &self.views {
+            match &view.pose {
+                Some(pose) => {
+                    rvecs.push(pose.rvec_mat()?);
+                    tvecs.push(pose.tvec_mat()?);
+                }
+                None => {
+                    rvecs.push(Mat::default());
+                    tvecs.push(Mat::default());
+                }
+            }
+        }
+        Ok((rvecs, tvecs))
+    }
+}
+
+/// 二乗平均平方根 (空の場合は None)
+pub(crate) fn rms(errors: &[f64]) -> Option<f64> {
+    if errors.is_empty() {
+        return None;
+    }
+    Some((errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt())
+}
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-12, "{} != {}", actual, expected);
+    }
+
+    /// 以前の compute_reprojection_error と同じ計算
+    fn previous_total_error(residuals: &[Option<Vec<f64>>]) -> f64 {
+        let errors: Vec<f64> = residuals
+            .iter()
+            .map(|errors| match errors {
+                Some(errors) if !errors.is_empty() => {
+                    (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt()
+                }
+                _ => 0.0,
+            })
+            .collect();
+        errors.iter().sum::<f64>() / residuals.len() as f64
+    }
+
+    #[test]
+    fn rms_weights_every_point_equally() {
+        // 1点で誤差 3 px のビューと、9点で誤差 1 px のビュー
+        let residuals = vec![Some(vec![3.0]), Some(vec![1.0; 9])];
+        let statistics = ReprojectionStatistics::new(&residuals);
+
+        assert_eq!(statistics.view_count, 2);
+        assert_eq!(statistics.point_count, 10);
+        assert_close(statistics.rms, ((9.0 + 9.0) / 10.0f64).sqrt());
+        assert_close(statistics.mean_view_rms, 2.0);
+        assert_close(statistics.max_view_rms, 3.0);
+        assert_close(statistics.max_point_error, 3.0);
+    }
+
+    #[test]
+    fn median_of_odd_and_even_view_counts() {
+        let odd = vec![Some(vec![1.0]), Some(vec![5.0]), Some(vec![2.0])];
+        assert_close(ReprojectionStatistics::new(&odd).median_view_rms, 2.0);
+
+        let even = vec![Some(vec![10.0]), Some(vec![1.0]), Some(vec![4.0]), Some(vec![2.0])];
+        assert_close(ReprojectionStatistics::new(&even).median_view_rms, 3.0);
+
+        assert_close(median(&[]), 0.0);
+    }
+
+    #[test]
+    fn failed_projections_are_counted_and_excluded() {
+        let residuals = vec![Some(vec![1.0, 1.0]), None, Some(vec![2.0])];
+        let statistics = ReprojectionStatistics::new(&residuals);
+
+        assert_eq!(statistics.view_count, 3);
+        assert_eq!(statistics.failed_view_count, 1);
+        assert_eq!(statistics.point_count, 3);
+        assert_close(statistics.rms, (6.0 / 3.0f64).sqrt());
+        assert_close(statistics.mean_view_rms, 1.5);
+    }
+
+    #[test]
+    fn legacy_total_error_keeps_the_previous_formula() {
+        // 投影に失敗したビューは 0 として平均に含める
+        let residuals = vec![Some(vec![3.0]), None, Some(vec![1.0, 1.0]), Some(vec![0.5, 1.5, 2.5])];
+        let statistics = ReprojectionStatistics::new(&residuals);
+
+        assert_close(statistics.legacy_total_error, previous_total_error(&residuals));
+        assert_close(statistics.legacy_total_error, (3.0 + 0.0 + 1.0 + (8.75 / 3.0f64).sqrt()) / 4.0);
+    }
+
+    #[test]
+    fn empty_residuals_give_zero_statistics() {
+        let statistics = ReprojectionStatistics::new(&[]);
+        assert_eq!(statistics.view_count, 0);
+        assert_close(statistics.rms, 0.0);
+        assert_close(statistics.mean_view_rms, 0.0);
+        assert_close(statistics.legacy_total_error, 0.0);
+    }
+}
@@ -1,100 +1,108 @@
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use opencv::{core, Error as OpenCvError};
-use serde_json::json;
+use serde::Serialize;
 
-use crate::FILE_FORMAT;
+use crate::{
+    error::{CalibrationError, Result},
+    IMAGE_EXTENSIONS,
+};
 
 pub struct CustomFile {}
 
 impl CustomFile {
     /// 出力ディレクトリを作成する
-    pub fn create_out_dir(directory_path: Option<&str>) {
-        let out_dir = PathBuf::from("out");
+    pub fn create_out_dir(directory_path: &str) -> Result<()> {
+        let out_dir = PathBuf::from(directory_path);
 
         if !out_dir.exists() {
-            match fs::create_dir(&out_dir) {
-                Ok(_) => println!("Directory '{}' created", out_dir.display()),
-                Err(e) => {
-                    eprintln!("Failed to create base directory '{}': {}", out_dir.display(), e);
-                    return;
-                }
-            }
-        }
-
-        if let Some(path) = directory_path {
-            let sub_dir = out_dir.join(path);
-            if !sub_dir.exists() {
-                match fs::create_dir(&sub_dir) {
-                    Ok(_) => println!("Subdirectory '{}' created", sub_dir.display()),
-                    Err(e) => eprintln!("Failed to create subdirectory '{}': {}", sub_dir.display(), e),
-                }
-            }
+            fs::create_dir_all(&out_dir).map_err(|e| CalibrationError::io(directory_path, e))?;
+            println!("Directory '{}' created", out_dir.display());
         }
+        Ok(())
     }
 
-    /// 指定したディレクトリ内の画像パスを取得する
-    pub fn get_image_paths(dir: &str) -> Vec<std::path::PathBuf> {
-        std::fs::read_dir(dir)
-            .unwrap()
-            .filter_map(Result::ok)
-            .filter(|entry| entry.path().extension().map(|ext| ext == FILE_FORMAT).unwrap_or(false))
-            .map(|entry| entry.path())
-            .collect()
-    }
+    /// 画像パスを取得する
+    ///
+    /// `source` はディレクトリまたはグロブパターン (例: `./img/**/*.png`)。
+    /// 拡張子は大文字小文字を区別せずに `extensions` と比較し、結果はパスの順に並べる
+    pub fn get_image_paths(source: &str, extensions: &[String], recursive: bool) -> Result<Vec<PathBuf>> {
+        let mut image_paths = if is_glob_pattern(source) {
+            glob::glob(source)
+                .map_err(|e| CalibrationError::Config(format!("invalid glob pattern '{}': {}", source, e)))?
+                .filter_map(|path| path.ok())
+                .filter(|path| path.is_file() && has_extension(path, extensions))
+                .collect()
+        } else {
+            let mut image_paths = Vec::new();
+            collect_image_paths(Path::new(source), extensions, recursive, &mut image_paths)?;
+            image_paths
+        };
 
-    /// 画像の読み込みに失敗したファイルのリストをJSON形式で出力
-    pub fn create_output_json(file_path: &str, json_data: Vec<String>) -> opencv::Result<(), OpenCvError> {
-        // 失敗した画像のリストをJSON形式で出力
-        if json_data.is_empty() {
-            println!("No failed images to report.");
-            return Ok(());
-        }
+        image_paths.sort();
+        Ok(image_paths)
+    }
 
-        println!("Failed to read image: {:?}", json_data);
+    /// 既定の拡張子 (IMAGE_EXTENSIONS)
+    pub fn default_image_extensions() -> Vec<String> {
+        IMAGE_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+    }
 
-        // JSONデータを作成
-        let failed_json = json!({ "failed_read_json": json_data });
-        let pretty_json = match serde_json::to_string_pretty(&failed_json) {
-            Ok(json) => json,
-            Err(e) => {
-                eprintln!("JSON serialize error: {}", e);
-                return Err(OpenCvError::new(core::StsError, format!("JSON serialize error: {}", e)));
-            }
-        };
+    /// シリアライズ可能なデータをJSON形式で出力
+    pub fn write_json<T: Serialize>(file_path: &str, data: &T) -> Result<()> {
+        let pretty_json = serde_json::to_string_pretty(data).map_err(|e| CalibrationError::serialization(file_path, e))?;
 
         // JSONファイルの書き込み
         println!("Writing JSON to: {}", file_path);
 
         // 出力先のディレクトリ作成
-        let binding = PathBuf::from(file_path);
-        let dir = binding.parent();
-        if let Some(dir_path) = dir {
-            if !dir_path.exists() {
-                if let Err(e) = fs::create_dir_all(dir_path) {
-                    eprintln!("Failed to create directory: {}", e);
-                    return Err(OpenCvError::new(core::StsError, format!("Failed to create directory: {}", e)));
-                }
+        if let Some(dir_path) = Path::new(file_path).parent() {
+            if !dir_path.as_os_str().is_empty() && !dir_path.exists() {
+                fs::create_dir_all(dir_path).map_err(|e| CalibrationError::io(dir_path.to_string_lossy(), e))?;
             }
         }
 
         // ファイル作成と書き込み
-        match File::create(file_path) {
-            Ok(mut file) => {
-                if let Err(e) = file.write_all(pretty_json.as_bytes()) {
-                    eprintln!("File write error: {}", e);
-                    return Err(OpenCvError::new(core::StsError, format!("File write error: {}", e)));
-                }
-                println!("JSON successfully written.");
-            }
-            Err(e) => {
-                eprintln!("File create error: {}", e);
-                return Err(OpenCvError::new(core::StsError, format!("File create error: {}", e)));
-            }
-        }
+        let mut file = File::create(file_path).map_err(|e| CalibrationError::io(file_path, e))?;
+        file.write_all(pretty_json.as_bytes()).map_err(|e| CalibrationError::io(file_path, e))?;
+        println!("JSON successfully written.");
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// JSONファイルを読み込む
+    pub fn read_json<T: serde::de::DeserializeOwned>(file_path: &str) -> Result<T> {
+        let json = fs::read_to_string(file_path).map_err(|e| CalibrationError::io(file_path, e))?;
+        serde_json::from_str(&json).map_err(|e| CalibrationError::serialization(file_path, e))
+    }
+}
+
+/// ディレクトリ内の画像パスを集める (recursive の場合はサブディレクトリも辿る)
+fn collect_image_paths(dir: &Path, extensions: &[String], recursive: bool, image_paths: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir).map_err(|e| CalibrationError::io(dir.to_string_lossy(), e))?;
+
+    for entry in entries {
+        let path = entry.map_err(|e| CalibrationError::io(dir.to_string_lossy(), e))?.path();
+        if path.is_dir() {
+            if recursive {
+                collect_image_paths(&path, extensions, recursive, image_paths)?;
+            }
+        } else if has_extension(&path, extensions) {
+            image_paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 拡張子が一致するか (大文字小文字を区別しない)
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(ext)))
+        .unwrap_or(false)
+}
+
+fn is_glob_pattern(source: &str) -> bool {
+    source.contains(['*', '?', '['])
+}
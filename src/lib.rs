@@ -0,0 +1,52 @@
+//! カメラキャリブレーションのライブラリ
+//!
+//! CLI (`camera_calibrate`) と同じ検出・キャリブレーションを他のプログラムから呼び出せる。
+//! 通常は [`Calibrator`] でターゲット・モデル・画像を指定し、[`CalibrationResult`] を受け取る
+
+pub mod calibrator;
+pub mod camera_calibration;
+pub mod camera_model;
+pub mod command_line;
+pub mod compare;
+pub mod config;
+pub mod detection_cache;
+pub mod error;
+pub mod evaluation;
+pub mod file;
+pub mod pose;
+pub mod report;
+pub mod target;
+pub mod target_generator;
+pub mod video;
+pub mod view_check;
+pub mod visualization;
+
+pub use calibrator::Calibrator;
+pub use camera_model::{CalibrationResult, Distortion, ImageSize, Intrinsics, Metadata, Pose};
+pub use command_line::{CalibrationPattern, ChessboardDetector};
+pub use config::Config;
+pub use error::{CalibrationError, Result};
+pub use target::TargetDescription;
+
+// IMAGE FORMAT READ & DETECTED
+pub const IMAGE_EXTENSIONS: [&str; 7] = ["jpeg", "jpg", "png", "tif", "tiff", "bmp", "webp"];
+
+// OUTPUT FILE NAME
+pub const DETECTION_REPORT_FILE_NAME: &str = "detection_report.json";
+pub const DETECTOR_REPORT_FILE_NAME: &str = "detector_report.json";
+pub const DETECTION_CACHE_FILE_NAME: &str = "detections.json";
+pub const RESULT_IMAGE_FILE_NAME: &str = "result.jpeg";
+pub const COVERAGE_IMAGE_FILE_NAME: &str = "coverage.jpeg";
+pub const RESIDUALS_DIR_NAME: &str = "residuals";
+pub const RESIDUAL_QUIVER_FILE_NAME: &str = "residual_quiver.jpeg";
+pub const DISTORTION_MAGNITUDE_FILE_NAME: &str = "distortion_magnitude.jpeg";
+pub const DISTORTION_VECTORS_FILE_NAME: &str = "distortion_vectors.jpeg";
+pub const DISTORTION_RADIAL_FILE_NAME: &str = "distortion_radial.jpeg";
+pub const REPORT_FILE_NAME: &str = "report.html";
+pub const CALIBRATION_JSON_FILE_NAME: &str = "calibration.json";
+pub const EVALUATION_FILE_NAME: &str = "evaluation.json";
+pub const POSE_FILE_NAME: &str = "pose.json";
+pub const POSE_AXES_FILE_NAME: &str = "pose_axes.jpeg";
+pub const EFFECTIVE_CONFIG_FILE_NAME: &str = "config.toml";
+pub const FRAMES_DIR_NAME: &str = "frames";
+pub const FRAME_REPORT_FILE_NAME: &str = "frame_report.json";
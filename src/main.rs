@@ -1,101 +1,252 @@
 use std::time::Instant;
 
-use opencv::core::{
-    TermCriteria,
-    TermCriteria_Type,
-    Size
-};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use opencv::core::{Mat, Point2f, Size, Vector};
+use opencv::prelude::*;
 use clap::Parser;
 
-use camera_calibration::{CameraCalibration, CameraCalibrationTrait};
-use file::CustomFile;
-use command_line::{Args, CalibrationPattern};
-mod camera_calibration;
-mod file;
-mod command_line;
+use camera_calibrate::camera_calibration::{
+    CameraCalibration, CameraCalibrationTrait, Detection, DetectionReport, DetectorReport, ImageReport, ImageStatus,
+};
+use camera_calibrate::file::CustomFile;
+use camera_calibrate::command_line::{
+    Args, CalibrationPattern, ChessboardDetector, Command, CompareArgs, ConvertArgs, DistortionArgs, EvaluateArgs, GenerateTargetArgs, MigrateArgs,
+    PoseArgs, ReportArgs, UndistortArgs,
+};
+use camera_calibrate::calibrator::Calibrator;
+use camera_calibrate::camera_model::{CalibrationResult, ImageSize, SCHEMA_VERSION};
+use camera_calibrate::compare::CalibrationComparison;
+use camera_calibrate::evaluation::Evaluation;
+use camera_calibrate::pose::{PoseEstimate, RansacOptions};
+use camera_calibrate::config::Config;
+use camera_calibrate::error::{CalibrationError, Result};
+use camera_calibrate::detection_cache::{CacheEntry, DetectionCache};
+use camera_calibrate::report::{HtmlReport, ReportContent};
+use camera_calibrate::target::TargetDescription;
+use camera_calibrate::target_generator::TargetGenerator;
+use camera_calibrate::video::VideoSampler;
+use camera_calibrate::view_check::ViewCheck;
+use camera_calibrate::visualization::Visualization;
+use camera_calibrate::{
+    CALIBRATION_JSON_FILE_NAME, COVERAGE_IMAGE_FILE_NAME, DETECTION_CACHE_FILE_NAME, DETECTION_REPORT_FILE_NAME,
+    DETECTOR_REPORT_FILE_NAME, DISTORTION_MAGNITUDE_FILE_NAME, DISTORTION_RADIAL_FILE_NAME, DISTORTION_VECTORS_FILE_NAME,
+    EFFECTIVE_CONFIG_FILE_NAME, EVALUATION_FILE_NAME, FRAMES_DIR_NAME, FRAME_REPORT_FILE_NAME, POSE_AXES_FILE_NAME, POSE_FILE_NAME,
+    REPORT_FILE_NAME, RESIDUALS_DIR_NAME, RESIDUAL_QUIVER_FILE_NAME, RESULT_IMAGE_FILE_NAME,
+};
 
-// IMAGE FORMAT READ & DETECTED 
-const FILE_FORMAT: &str = "jpeg";
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
 
-// DISPLAY IMAGE WINDOW SIZE
-const WINDOW_TITLE: &str = "Chessboard Corners Detection";
-const GUI_WINDOW_WIDTH: i32 = 900;
-const GUI_WINDOW_HEIGHT: i32 = 700;
-const WAIT_KEY_DELAY: i32 = 1000;
+fn run(args: &Args) -> Result<()> {
+    match &args.command {
+        Command::Calibrate(run_args) => calibrate(&Config::load(run_args)?),
+        Command::Detect(run_args) => detect(&Config::load(run_args)?),
+        Command::Undistort(undistort_args) => undistort(undistort_args),
+        Command::Evaluate(evaluate_args) => evaluate(evaluate_args),
+        Command::Convert(convert_args) => convert(convert_args),
+        Command::Report(report_args) => report(report_args),
+        Command::Distortion(distortion_args) => distortion(distortion_args),
+        Command::Migrate(migrate_args) => migrate(migrate_args),
+        Command::Compare(compare_args) => compare(compare_args),
+        Command::Pose(pose_args) => pose(pose_args),
+        Command::GenerateTarget(target_args) => generate_target(target_args),
+    }
+}
 
-// TEXT
-const TEXT_POINT: (i32, i32) = (10, 100);
-const TEXT_FONT_SCALE: f64 = 3.0;
-const TEXT_COLOR: (f64, f64, f64, f64) = (0.0, 255.0, 0.0, 0.0); // GREEN
+/// 出力ディレクトリを準備し、設定されたパターンを全画像から検出する
+/// 検出キャッシュが有効な場合、ハッシュが変わっていない画像はキャッシュの結果を使う
+fn detect_pattern(config: &Config) -> Result<(Vec<Detection>, Vec<ImageReport>)> {
+    CustomFile::create_out_dir(&config.output.directory)?;
+    config.save_effective(&config.output_path(EFFECTIVE_CONFIG_FILE_NAME))?;
 
-fn main() -> opencv::Result<()> {
-    CustomFile::create_out_dir(None);
+    let target = TargetDescription::load(&config.dataset.target)?;
+    let image_paths = match &config.video.path {
+        Some(video_path) => VideoSampler::extract_frames(
+            video_path,
+            &config.video,
+            &config.output_path(FRAMES_DIR_NAME),
+            &config.output_path(FRAME_REPORT_FILE_NAME),
+        )?,
+        None => CustomFile::get_image_paths(&config.dataset.images, &config.dataset.extensions, config.dataset.recursive)?,
+    };
 
-    let args = Args::parse();
-    let _ = match args.calibrate {
-        Some(CalibrationPattern::ChessBoard) => chessboard(),
-        Some(CalibrationPattern::SymmetricCircleGrid) => symmetric_circle_grid(),
-        Some(CalibrationPattern::AsymmetricCircleGrid) => unimplemented!(),
-        Some(CalibrationPattern::ChArUco) => unimplemented!(),
-        None => unimplemented!(),
+    let cache_path = config.output_path(DETECTION_CACHE_FILE_NAME);
+    let settings = config.detection_settings(&target);
+    let mut cache = if config.detection.cache {
+        DetectionCache::load(&cache_path, &settings)
+    } else {
+        DetectionCache::new(&settings)
     };
+    let (cached, pending) = cache.partition(&image_paths)?;
+    if !cached.is_empty() {
+        println!("Reusing cached detections for {} of {} images", cached.len(), image_paths.len());
+    }
+    let pending_paths: Vec<PathBuf> = pending.iter().map(|(image_path, _)| image_path.clone()).collect();
 
-    Ok(())
-}
+    let mut image_reports = Vec::new();
+    let mut detector_reports = Vec::new();
+    let detected = Calibrator::detect_images(config, &target, &pending_paths, &mut image_reports, &mut detector_reports)?;
+
+    // 今回検出した画像をキャッシュに加え、入力画像の順序で結果を並べる
+    // 検出結果は Detected に分類された画像と同じ順序で返される
+    let mut detected = detected.into_iter();
+    let mut entries = cached;
+    for report in image_reports {
+        let Some((_, file_hash)) = pending.iter().find(|(image_path, _)| image_path.to_string_lossy() == report.image_path) else {
+            continue;
+        };
+        let detection = match report.status {
+            ImageStatus::Detected => detected.next(),
+            _ => None,
+        };
+        let detector = detector_reports.iter().find(|detector| detector.image_path == report.image_path).cloned();
+        entries.push(CacheEntry {
+            image_path: report.image_path.clone(),
+            file_hash: file_hash.clone(),
+            report,
+            detection,
+            detector,
+        });
+    }
+    entries.sort_by_key(|entry| image_paths.iter().position(|p| p.to_string_lossy() == entry.image_path));
+    cache.entries = entries;
+    if let Err(e) = cache.save(&cache_path) {
+        eprintln!("Failed to save the detection cache: {}", e);
+    }
+
+    let detection_report = DetectionReport::new(cache.entries.iter().map(|entry| &entry.report).collect());
+    detection_report.print_summary();
+    CustomFile::write_json(&config.output_path(DETECTION_REPORT_FILE_NAME), &detection_report)?;
 
-fn chessboard() -> opencv::Result<()> {
-    // FILE & DIRECTORY PATH
-    const CHESSBOARD_DIRECTORY_NAME: &str = "chessboard";
-    const FAILED_READ_IMAGES_PATH: &str = "./out/chessboard/failed_read_files.json";
-    const READ_DATASET_PATH: &str = "./img/chessboard_dataset";
-    const UNDISTORT_IMAGE_PATH: &str = "./img/chessboard_dataset/calib04.jpeg";
-    const RESULT_IMAGE_PATH: &str = "./out/chessboard/result.jpeg";
-    const CALIBRATION_JSON_PATH: &str = "./out/chessboard/calibration.json";
-    // CAMERA CALIBRATION PARAMETERS
-    const CHESSBOARD_SIZE: (i32, i32) = (9, 6);
-    const FRAME_WIDTH: i32 = 1440;
-    const FRAME_HEIGHT: i32 = 1080;
-    const CRITERIA_MAX_COUNT: i32 = 30;
-    const CRITERIA_EPS: f64 = 0.001;
-    // CAMERA CALIBRATION PARAMETERS
-    const CORNER_SUB_PIX_WINDOW_WIDTH: i32 = 11;
-    const CORNER_SUB_PIX_WINDOW_HEIGHT: i32 = 11;
-    const CORNER_SUB_PIX_ZERO_ZONE: i32 = -1;
+    // キャッシュから再利用した画像も含めて、全画像の検出器ごとの結果を書き出す
+    if config.pattern == CalibrationPattern::ChessBoard {
+        let detector_reports: Vec<DetectorReport> = cache.entries.iter().filter_map(|entry| entry.detector.clone()).collect();
+        if config.detection.detector == ChessboardDetector::Both {
+            DetectorReport::print_summary(&detector_reports);
+        }
+        CustomFile::write_json(&config.output_path(DETECTOR_REPORT_FILE_NAME), &detector_reports)?;
+    }
+
+    let detections = cache.entries.iter().filter_map(|entry| entry.detection.clone()).collect();
+    let image_reports = cache.entries.into_iter().map(|entry| entry.report).collect();
+    Ok((detections, image_reports))
+}
 
+fn calibrate(config: &Config) -> Result<()> {
     let start_time = Instant::now();
 
-    let chessboard_size = Size::new(CHESSBOARD_SIZE.0, CHESSBOARD_SIZE.1);
-    let image_paths = CustomFile::get_image_paths(READ_DATASET_PATH);
-    let criteria = TermCriteria::new(
-        (TermCriteria_Type::COUNT as i32) + (TermCriteria_Type::EPS as i32),
-        CRITERIA_MAX_COUNT,
-        CRITERIA_EPS,
+    let (detections, image_reports) = detect_pattern(config)?;
+    let (obj_points, img_points) = Detection::to_point_vectors(&detections);
+    let criteria = config.calibration.criteria.to_term_criteria()?;
+    let detection_time = start_time.elapsed();
+
+    let frame_size = Detection::image_size(&detections)?;
+    write_coverage_image(config, &detections, &img_points, frame_size)?;
+    ViewCheck::run(&obj_points, &img_points, frame_size, &config.checks)?;
+
+    let calibration_start_time = Instant::now();
+    let output = CameraCalibration::calibrate_camera(
+        &obj_points,
+        &img_points,
+        frame_size,
+        config.calibration.model.to_flags(),
+        criteria,
+    )?;
+    let calibration_time = calibration_start_time.elapsed();
+
+    let output_start_time = Instant::now();
+    if let Some(undistort_image) = preview_image(config, &detections) {
+        CameraCalibration::undistort_image(
+            &output.camera_matrix,
+            &output.dist_coeffs,
+            &undistort_image,
+            &config.output_path(RESULT_IMAGE_FILE_NAME),
+        )?;
+    }
+
+    let statistics = CameraCalibration::compute_reprojection_error(
+        &obj_points,
+        &img_points,
+        &output.rvecs,
+        &output.tvecs,
+        &output.camera_matrix,
+        &output.dist_coeffs,
     )?;
+    statistics.print();
 
-    let mut read_image_cnt = 0;
-    let (obj_points, img_points) =
-        CameraCalibration::detect_chessboard_corners(
-            &image_paths,
-            chessboard_size,
-            criteria,
-            &mut read_image_cnt,
-            FAILED_READ_IMAGES_PATH,
-            CORNER_SUB_PIX_WINDOW_WIDTH,
-            CORNER_SUB_PIX_WINDOW_HEIGHT,
-            CORNER_SUB_PIX_ZERO_ZONE,
+    if config.output.residual_images {
+        Visualization::residual_images(
+            &detections,
+            &output.rvecs,
+            &output.tvecs,
+            &output.camera_matrix,
+            &output.dist_coeffs,
+            frame_size,
+            config.output.residual_scale,
+            &config.output_path(RESIDUALS_DIR_NAME),
+            &config.output_path(RESIDUAL_QUIVER_FILE_NAME),
         )?;
+    }
 
-    let frame_size = Size::new(FRAME_WIDTH, FRAME_HEIGHT);
-    let (camera_matrix, dist_coeffs, rvecs, tvecs) =
-        CameraCalibration::calibrate_camera(&obj_points, &img_points, frame_size, criteria)?;
+    let observed_radius = observed_radius(&img_points, &output.camera_matrix)?;
+    Visualization::distortion_images(
+        &output.camera_matrix,
+        &output.dist_coeffs,
+        frame_size,
+        Some(observed_radius),
+        &config.output_path(DISTORTION_MAGNITUDE_FILE_NAME),
+        &config.output_path(DISTORTION_VECTORS_FILE_NAME),
+        &config.output_path(DISTORTION_RADIAL_FILE_NAME),
+    )?;
 
-    CameraCalibration::undistort_image(&camera_matrix, &dist_coeffs, UNDISTORT_IMAGE_PATH, RESULT_IMAGE_PATH, CHESSBOARD_DIRECTORY_NAME)?;
+    let image_names: Vec<String> = detections.iter().map(|detection| detection.file_name.clone()).collect();
+    let units = TargetDescription::load(&config.dataset.target)?.units;
+    let result = CalibrationResult::new(&output, &statistics, frame_size, &image_names, config.pattern, units)?;
+    result.save(&config.output_path(CALIBRATION_JSON_FILE_NAME))?;
+    let output_time = output_start_time.elapsed();
 
-    let error = CameraCalibration::compute_reprojection_error(&obj_points, &img_points, &rvecs, &tvecs, &camera_matrix, &dist_coeffs)?;
-    println!("Total Error: {}", error);
+    let duration = start_time.elapsed();
+    println!("Processing time: {:?}", duration);
 
-    if let Err(e) = CameraCalibration::save_to_json(&camera_matrix, &dist_coeffs, &rvecs, &tvecs, error, CALIBRATION_JSON_PATH) {
-        eprintln!("Failed to save to json: {}", e);
+    if config.output.html_report {
+        let timings = [
+            ("detection", detection_time),
+            ("calibration", calibration_time),
+            ("outputs", output_time),
+            ("total", duration),
+        ];
+        let content = ReportContent {
+            config,
+            output: &output,
+            statistics: &statistics,
+            detections: &detections,
+            image_reports: &image_reports,
+            timings: &timings,
+        };
+        HtmlReport::write(&content, &config.output_path(REPORT_FILE_NAME))?;
+    }
+
+    Ok(())
+}
+
+/// 検出のみを行い、検出レポートを出力する
+fn detect(config: &Config) -> Result<()> {
+    let start_time = Instant::now();
+
+    let (detections, _) = detect_pattern(config)?;
+    if !detections.is_empty() {
+        let (_, img_points) = Detection::to_point_vectors(&detections);
+        write_coverage_image(config, &detections, &img_points, Detection::image_size(&detections)?)?;
     }
 
     let duration = start_time.elapsed();
@@ -104,52 +255,267 @@ fn chessboard() -> opencv::Result<()> {
     Ok(())
 }
 
-fn symmetric_circle_grid() -> opencv::Result<()> {
-    // FILE & DIRECTORY PATH
-    const CIRCLE_GRID_DIRECTORY_NAME: &str = "circle_grid";
-    const FAILED_READ_IMAGES_PATH: &str = "./out/circle_grid/failed_read_files.json";
-    const READ_DATASET_PATH: &str = "./img/circle_grid_dataset";
-    const UNDISTORT_IMAGE_PATH: &str = "./img/circle_grid_dataset/calib04.jpeg";
-    const RESULT_IMAGE_PATH: &str = "./out/circle_grid/result.jpeg";
-    const CALIBRATION_JSON_PATH: &str = "./out/circle_grid/calibration.json";
-    // CAMERA CALIBRATION PARAMETERS
-    const CHESSBOARD_SIZE: (i32, i32) = (9, 6);
-    const FRAME_WIDTH: i32 = 1440;
-    const FRAME_HEIGHT: i32 = 1080;
-    const CRITERIA_MAX_COUNT: i32 = 30;
-    const CRITERIA_EPS: f64 = 0.001;
+/// 検出点の被覆ヒートマップを出力する
+fn write_coverage_image(
+    config: &Config,
+    detections: &[Detection],
+    img_points: &Vector<Vector<Point2f>>,
+    frame_size: Size,
+) -> Result<()> {
+    Visualization::coverage_heatmap(
+        img_points,
+        frame_size,
+        config.checks.coverage_grid,
+        preview_image(config, detections).as_deref(),
+        &config.output_path(COVERAGE_IMAGE_FILE_NAME),
+    )
+}
 
-    let start_time = Instant::now();
+/// 歪み補正の確認や被覆ヒートマップの背景に使う画像
+/// 設定された画像が無い場合 (動画入力など) は、最初に検出できた画像を使う
+fn preview_image(config: &Config, detections: &[Detection]) -> Option<String> {
+    if Path::new(&config.dataset.undistort_image).exists() {
+        return Some(config.dataset.undistort_image.clone());
+    }
+    detections.first().map(|detection| detection.image_path.clone())
+}
 
-    let chessboard_size = Size::new(CHESSBOARD_SIZE.0, CHESSBOARD_SIZE.1);
-    let image_paths = CustomFile::get_image_paths(READ_DATASET_PATH);
-    let criteria = TermCriteria::new(
-        (TermCriteria_Type::COUNT as i32) + (TermCriteria_Type::EPS as i32),
-        CRITERIA_MAX_COUNT,
-        CRITERIA_EPS,
-    )?;
+/// 検出点のうち主点から最も遠い点までの距離 [px]
+fn observed_radius(img_points: &Vector<Vector<Point2f>>, camera_matrix: &Mat) -> Result<f64> {
+    let cx = *camera_matrix.at_2d::<f64>(0, 2)?;
+    let cy = *camera_matrix.at_2d::<f64>(1, 2)?;
+    Ok(img_points
+        .iter()
+        .flatten()
+        .map(|point| (point.x as f64 - cx).hypot(point.y as f64 - cy))
+        .fold(0.0, f64::max))
+}
+
+/// 保存済みのキャリブレーションの歪みモデルを画像として出力する
+fn distortion(args: &DistortionArgs) -> Result<()> {
+    let calibration = CalibrationResult::load(&args.calibration)?;
+    let camera_matrix = calibration.intrinsics.to_mat()?;
+    let dist_coeffs = calibration.distortion.to_mat()?;
+
+    // 画像サイズが指定されていなければ、キャリブレーション結果の画像サイズを使う
+    let frame_size = match image_size_arg(args.width, args.height)? {
+        Some(image_size) => image_size.to_size(),
+        None => calibration.image_size.to_size(),
+    };
+    if frame_size.width <= 0 || frame_size.height <= 0 {
+        return Err(CalibrationError::Config(format!(
+            "invalid image size {}x{}",
+            frame_size.width, frame_size.height
+        )));
+    }
 
-    let mut read_image_cnt = 0;
-    let (obj_points, img_points) =
-        CameraCalibration::detect_circle_grid(&image_paths, chessboard_size, &mut read_image_cnt, FAILED_READ_IMAGES_PATH)?;
+    CustomFile::create_out_dir(&args.output_dir)?;
+    let output_path = |file_name: &str| PathBuf::from(&args.output_dir).join(file_name).to_string_lossy().into_owned();
+    Visualization::distortion_images(
+        &camera_matrix,
+        &dist_coeffs,
+        frame_size,
+        None,
+        &output_path(DISTORTION_MAGNITUDE_FILE_NAME),
+        &output_path(DISTORTION_VECTORS_FILE_NAME),
+        &output_path(DISTORTION_RADIAL_FILE_NAME),
+    )
+}
 
-    let frame_size = Size::new(FRAME_WIDTH, FRAME_HEIGHT);
-    println!("Reached here: Before calibrate_camera");
-    let (camera_matrix, dist_coeffs, rvecs, tvecs) =
-        CameraCalibration::calibrate_camera(&obj_points, &img_points, frame_size, criteria)?;
+/// 保存済みのキャリブレーションで画像の歪みを補正する
+fn undistort(args: &UndistortArgs) -> Result<()> {
+    let calibration = CalibrationResult::load(&args.calibration)?;
+    let camera_matrix = calibration.intrinsics.to_mat()?;
+    let dist_coeffs = calibration.distortion.to_mat()?;
 
-    println!("Reached here: Before undistort_image");
-    CameraCalibration::undistort_image(&camera_matrix, &dist_coeffs, UNDISTORT_IMAGE_PATH, RESULT_IMAGE_PATH, CIRCLE_GRID_DIRECTORY_NAME)?;
+    let input = Path::new(&args.input);
+    let image_paths = if input.is_dir() {
+        CustomFile::get_image_paths(&args.input, &CustomFile::default_image_extensions(), false)?
+    } else {
+        vec![input.to_path_buf()]
+    };
 
-    let error = CameraCalibration::compute_reprojection_error(&obj_points, &img_points, &rvecs, &tvecs, &camera_matrix, &dist_coeffs)?;
-    println!("Total Error: {}", error);
+    CustomFile::create_out_dir(&args.output_dir)?;
+    for image_path in &image_paths {
+        let file_name = image_path.file_name().unwrap_or_default();
+        let result_image_path = PathBuf::from(&args.output_dir).join(file_name);
+        CameraCalibration::undistort_image(
+            &camera_matrix,
+            &dist_coeffs,
+            &image_path.to_string_lossy(),
+            &result_image_path.to_string_lossy(),
+        )?;
+        println!("Undistorted: {}", result_image_path.display());
+    }
+
+    Ok(())
+}
 
-    if let Err(e) = CameraCalibration::save_to_json(&camera_matrix, &dist_coeffs, &rvecs, &tvecs, error, CALIBRATION_JSON_PATH) {
-        eprintln!("Failed to save to json: {}", e);
+/// 保存済みのキャリブレーションを新しい画像セットで評価する (再推定はしない)
+fn evaluate(args: &EvaluateArgs) -> Result<()> {
+    let calibration = CalibrationResult::load(&args.calibration)?;
+
+    let config = Config::load(&args.run)?;
+    let (detections, _) = detect_pattern(&config)?;
+    let evaluation = Evaluation::run(&calibration, &args.calibration, &detections)?;
+    evaluation.print();
+
+    if config.output.residual_images {
+        let (rvecs, tvecs) = evaluation.pose_mats()?;
+        Visualization::residual_images(
+            &detections,
+            &rvecs,
+            &tvecs,
+            &calibration.intrinsics.to_mat()?,
+            &calibration.distortion.to_mat()?,
+            calibration.image_size.to_size(),
+            config.output.residual_scale,
+            &config.output_path(RESIDUALS_DIR_NAME),
+            &config.output_path(RESIDUAL_QUIVER_FILE_NAME),
+        )?;
     }
 
-    let duration = start_time.elapsed();
-    println!("Processing time: {:?}", duration);
+    CustomFile::write_json(&config.output_path(EVALUATION_FILE_NAME), &evaluation)?;
+    Ok(())
+}
+
+/// キャリブレーション結果を OpenCV の FileStorage 形式に変換する
+fn convert(args: &ConvertArgs) -> Result<()> {
+    let calibration = CalibrationResult::load(&args.input)?;
+    calibration.write_file_storage(&args.output)?;
+    println!("Converted '{}' to '{}'", args.input, args.output);
+    Ok(())
+}
+
+/// キャリブレーション結果の概要を表示する
+fn report(args: &ReportArgs) -> Result<()> {
+    let calibration = CalibrationResult::load(&args.calibration)?;
+    let intrinsics = &calibration.intrinsics;
+    let metadata = &calibration.metadata;
+
+    println!("Calibration: {} (schema version {})", args.calibration, calibration.schema_version);
+    println!("Image size: {}x{}", calibration.image_size.width, calibration.image_size.height);
+    if let Some(pattern) = metadata.pattern {
+        println!("Pattern: {:?}", pattern);
+    }
+    if let Some(created_at) = &metadata.created_at {
+        println!("Created at: {} ({})", created_at, metadata.tool);
+    }
+    println!("Views: {}", calibration.poses.len());
+    match metadata.rms {
+        Some(rms) => println!("RMS Error: {}", rms),
+        None => println!("RMS Error: unknown (migrated from the legacy format)"),
+    }
+    match &metadata.reprojection {
+        Some(statistics) => statistics.print(),
+        None => println!("Total Error (legacy): {}", metadata.total_error),
+    }
+    println!("fx: {}, fy: {}", intrinsics.fx, intrinsics.fy);
+    println!("cx: {}, cy: {}", intrinsics.cx, intrinsics.cy);
+    println!("Distortion Parameters: {:?}", calibration.distortion);
+    Ok(())
+}
 
+/// 旧形式の calibration.json を現在の形式に変換する
+fn migrate(args: &MigrateArgs) -> Result<()> {
+    let json: serde_json::Value = CustomFile::read_json(&args.input)?;
+    if json.get("schema_version").is_some() {
+        let calibration = CalibrationResult::load(&args.input)?;
+        println!("'{}' already uses schema version {}", args.input, calibration.schema_version);
+        return calibration.save(&args.output);
+    }
+
+    let legacy: CameraCalibration =
+        serde_json::from_value(json).map_err(|e| CalibrationError::serialization(&args.input, e))?;
+    let image_size = image_size_arg(args.width, args.height)?;
+    let mut calibration = CalibrationResult::from_legacy(&legacy, image_size, &args.input)?;
+    calibration.metadata.pattern = args.pattern;
+    calibration.metadata.units = args.units;
+    if image_size.is_none() {
+        println!(
+            "No --width/--height given. Assuming the principal point is at the image center: {}x{}",
+            calibration.image_size.width, calibration.image_size.height
+        );
+    }
+
+    calibration.save(&args.output)?;
+    println!("Migrated '{}' to schema version {}: {}", args.input, SCHEMA_VERSION, args.output);
     Ok(())
+}
+
+/// 2つのキャリブレーションを比較し、差が閾値を超える場合はエラーにする
+fn compare(args: &CompareArgs) -> Result<()> {
+    let reference = CalibrationResult::load(&args.reference)?;
+    let candidate = CalibrationResult::load(&args.candidate)?;
+
+    println!("A: {}", args.reference);
+    println!("B: {}", args.candidate);
+    let comparison = CalibrationComparison::compare(&reference, &candidate, args.max_displacement, args.max_sigma)?;
+    comparison.print();
+
+    if let Some(output) = &args.output {
+        CustomFile::write_json(output, &comparison)?;
+    }
+
+    if !comparison.passed {
+        return Err(CalibrationError::ComparisonFailed(comparison.failures.join("; ")));
+    }
+    Ok(())
+}
+
+/// 1枚の画像からターゲットの姿勢を推定し、座標軸を描いた画像と姿勢の JSON を出力する
+fn pose(args: &PoseArgs) -> Result<()> {
+    let calibration = CalibrationResult::load(&args.calibration)?;
+    let mut config = Config::load(&args.run)?;
+    config.preview.enabled = false;
+    let target = TargetDescription::load(&config.dataset.target)?;
+
+    let detection = PoseEstimate::detect(&config, &target, Path::new(&args.image))?;
+    let ransac = args.ransac.then(|| RansacOptions {
+        iterations: args.ransac_iterations,
+        reprojection_error: args.ransac_threshold,
+        ..RansacOptions::default()
+    });
+    let estimate = PoseEstimate::estimate(&calibration, &args.calibration, &detection, ransac)?;
+    estimate.print();
+
+    CustomFile::create_out_dir(&config.output.directory)?;
+    let axis_length = args.axis_length.unwrap_or(target.spacing * 3.0);
+    estimate.draw_axes(&calibration, axis_length, &config.output_path(POSE_AXES_FILE_NAME))?;
+    CustomFile::write_json(&config.output_path(POSE_FILE_NAME), &estimate)
+}
+
+/// --width と --height の組を画像サイズにする (両方とも省略した場合は None)
+fn image_size_arg(width: Option<i32>, height: Option<i32>) -> Result<Option<ImageSize>> {
+    match (width, height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => Ok(Some(ImageSize { width, height })),
+        (Some(width), Some(height)) => Err(CalibrationError::Config(format!("invalid image size {}x{}", width, height))),
+        (None, None) => Ok(None),
+        _ => Err(CalibrationError::Config("give both --width and --height, or neither".to_string())),
+    }
+}
+
+fn generate_target(args: &GenerateTargetArgs) -> Result<()> {
+    let (marker_size, tag_spacing, dictionary) = match args.pattern {
+        CalibrationPattern::ChArUco => (
+            Some(args.marker_size.unwrap_or(args.spacing * 0.7)),
+            None,
+            Some(args.dictionary.clone()),
+        ),
+        CalibrationPattern::AprilGrid => (None, Some(args.tag_spacing), Some("DICT_APRILTAG_36h11".to_string())),
+        _ => (None, None, None),
+    };
+
+    let target = TargetDescription {
+        pattern: args.pattern,
+        rows: args.rows,
+        cols: args.cols,
+        spacing: args.spacing,
+        marker_size,
+        tag_spacing,
+        dictionary,
+        units: Some(args.units),
+    };
+
+    TargetGenerator::generate(&target, args.margin.unwrap_or(args.spacing), args.dpi, &args.output)
 }
\ No newline at end of file
@@ -0,0 +1,319 @@
+use std::path::{Path, PathBuf};
+
+use opencv::{
+    calib3d,
+    core::{Mat, Vector},
+    imgcodecs,
+    prelude::*,
+};
+use serde::Serialize;
+
+use crate::{
+    calibrator::Calibrator,
+    camera_calibration::{read_image, CameraCalibration, CameraCalibrationTrait, Detection},
+    camera_model::{CalibrationResult, Pose},
+    config::Config,
+    error::{CalibrationError, Result},
+    evaluation::rms,
+    target::{TargetDescription, Units},
+};
+
+/// solvePnPRansac の設定
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct RansacOptions {
+    pub iterations: i32,
+    /// インライアとみなす再投影誤差の上限 [px]
+    pub reprojection_error: f32,
+    pub confidence: f64,
+}
+
+impl Default for RansacOptions {
+    /// OpenCV の既定値
+    fn default() -> Self {
+        Self {
+            iterations: 100,
+            reprojection_error: 8.0,
+            confidence: 0.99,
+        }
+    }
+}
+
+/// オイラー角 [deg] (R = Rz(yaw) * Ry(pitch) * Rx(roll))
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct EulerAngles {
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+/// 1枚の画像から推定したターゲットの姿勢 (ターゲット座標系からカメラ座標系への変換)
+#[derive(Serialize, Debug, Clone)]
+pub struct PoseEstimate {
+    /// 使用したキャリブレーションファイル
+    pub calibration: String,
+    pub image_path: String,
+    /// 検出に使用した検出器
+    pub detector: String,
+    pub pose: Pose,
+    pub rotation_matrix: [[f64; 3]; 3],
+    pub euler_angles: EulerAngles,
+    /// カメラからターゲットの原点までの距離 (tvec の長さ)
+    pub distance: f64,
+    /// tvec と distance の単位。None の場合はターゲット記述の spacing と同じ単位
+    pub units: Option<Units>,
+    pub point_count: usize,
+    /// RANSAC を使った場合のインライアの数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inlier_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ransac: Option<RansacOptions>,
+}
+
+impl PoseEstimate {
+    /// 1枚の画像から設定されたパターンを検出する
+    pub fn detect(config: &Config, target: &TargetDescription, image_path: &Path) -> Result<Detection> {
+        let mut image_reports = Vec::new();
+        let detections = Calibrator::detect_images(config, target, &[image_path.to_path_buf()], &mut image_reports, &mut Vec::new())?;
+        detections.into_iter().next().ok_or_else(|| {
+            let detail = image_reports
+                .into_iter()
+                .find_map(|report| report.detail)
+                .unwrap_or_else(|| "the pattern was not found".to_string());
+            CalibrationError::Detection(format!("'{}': {}", image_path.display(), detail))
+        })
+    }
+
+    /// 固定した内部パラメータで検出結果からターゲットの姿勢を求める
+    /// `ransac` を与えると solvePnPRansac で外れ値を除いて求める
+    pub fn estimate(
+        calibration: &CalibrationResult,
+        calibration_path: &str,
+        detection: &Detection,
+        ransac: Option<RansacOptions>,
+    ) -> Result<Self> {
+        let (width, height) = detection.image_size;
+        let image_size = calibration.image_size;
+        if (width, height) != (image_size.width, image_size.height) {
+            return Err(CalibrationError::Config(format!(
+                "the image size {}x{} differs from the calibrated image size {}x{}",
+                width, height, image_size.width, image_size.height
+            )));
+        }
+
+        let camera_matrix = calibration.intrinsics.to_mat()?;
+        let dist_coeffs = calibration.distortion.to_mat()?;
+        let object_points = detection.object_points();
+        let image_points = detection.image_points();
+
+        let mut rvec = Mat::default();
+        let mut tvec = Mat::default();
+        let mut inlier_count = None;
+        let solved = match ransac {
+            Some(options) => {
+                let mut inliers = Vector::<i32>::new();
+                let solved = calib3d::solve_pnp_ransac(
+                    &object_points,
+                    &image_points,
+                    &camera_matrix,
+                    &dist_coeffs,
+                    &mut rvec,
+                    &mut tvec,
+                    false,
+                    options.iterations,
+                    options.reprojection_error,
+                    options.confidence,
+                    &mut inliers,
+                    calib3d::SOLVEPNP_ITERATIVE,
+                )?;
+                inlier_count = Some(inliers.len());
+                solved
+            }
+            None => calib3d::solve_pnp(
+                &object_points,
+                &image_points,
+                &camera_matrix,
+                &dist_coeffs,
+                &mut rvec,
+                &mut tvec,
+                false,
+                calib3d::SOLVEPNP_ITERATIVE,
+            )?,
+        };
+        if !solved {
+            return Err(CalibrationError::Calibration(format!(
+                "could not estimate the pose of the target in '{}'",
+                detection.image_path
+            )));
+        }
+
+        let mut pose = Pose::from_mats(&detection.file_name, &rvec, &tvec)?;
+        let residuals = CameraCalibration::reprojection_residuals(
+            &Vector::from_iter([object_points]),
+            &Vector::from_iter([image_points]),
+            &Vector::from_iter([rvec]),
+            &Vector::from_iter([tvec]),
+            &camera_matrix,
+            &dist_coeffs,
+        )?;
+        pose.reprojection_error = residuals.first().and_then(|errors| errors.as_deref()).and_then(rms);
+
+        let rotation_matrix = pose.rotation_matrix()?;
+        let [tx, ty, tz] = pose.tvec;
+        Ok(Self {
+            calibration: calibration_path.to_string(),
+            image_path: detection.image_path.clone(),
+            detector: detection.detector.clone(),
+            euler_angles: euler_angles(&rotation_matrix),
+            rotation_matrix,
+            distance: (tx * tx + ty * ty + tz * tz).sqrt(),
+            units: calibration.metadata.units,
+            point_count: detection.image_points.len(),
+            inlier_count,
+            ransac,
+            pose,
+        })
+    }
+
+    /// ターゲットの座標軸 (X: 赤, Y: 緑, Z: 青) を画像に描いて保存する
+    /// `axis_length` はターゲット記述の単位
+    pub fn draw_axes(&self, calibration: &CalibrationResult, axis_length: f64, output_path: &str) -> Result<()> {
+        let mut img = read_image(&PathBuf::from(&self.image_path))?;
+        let thickness = (img.cols() / 400).max(2);
+        calib3d::draw_frame_axes(
+            &mut img,
+            &calibration.intrinsics.to_mat()?,
+            &calibration.distortion.to_mat()?,
+            &self.pose.rvec_mat()?,
+            &self.pose.tvec_mat()?,
+            axis_length as f32,
+            thickness,
+        )?;
+        imgcodecs::imwrite(output_path, &img, &Vector::new())?;
+        println!("Pose axes written to: {}", output_path);
+        Ok(())
+    }
+
+    pub fn print(&self) {
+        let units = self.units.map_or(String::new(), |units| format!(" {:?}", units).to_lowercase());
+        println!("Image: {} ({})", self.image_path, self.detector);
+        match self.inlier_count {
+            Some(inlier_count) => println!("Points: {} ({} inliers)", self.point_count, inlier_count),
+            None => println!("Points: {}", self.point_count),
+        }
+        println!("rvec: {:?}", self.pose.rvec);
+        println!("tvec: {:?}{}", self.pose.tvec, units);
+        println!("Rotation matrix:");
+        for row in &self.rotation_matrix {
+            println!("  [{:>10.6}, {:>10.6}, {:>10.6}]", row[0], row[1], row[2]);
+        }
+        println!(
+            "Euler angles (ZYX): roll {:.3} deg, pitch {:.3} deg, yaw {:.3} deg",
+            self.euler_angles.roll, self.euler_angles.pitch, self.euler_angles.yaw
+        );
+        println!("Distance: {:.4}{}", self.distance, units);
+        if let Some(error) = self.pose.reprojection_error {
+            println!("RMS Error: {:.4} px", error);
+        }
+    }
+}
+
+/// 回転行列を ZYX のオイラー角 [deg] に分解する
+/// pitch が ±90° のとき (ジンバルロック) は roll を 0 とする
+fn euler_angles(rotation: &[[f64; 3]; 3]) -> EulerAngles {
+    let cos_pitch = rotation[0][0].hypot(rotation[1][0]);
+    let (roll, pitch, yaw) = if cos_pitch > 1e-9 {
+        (
+            rotation[2][1].atan2(rotation[2][2]),
+            (-rotation[2][0]).atan2(cos_pitch),
+            rotation[1][0].atan2(rotation[0][0]),
+        )
+    } else {
+        (0.0, (-rotation[2][0]).atan2(cos_pitch), (-rotation[0][1]).atan2(rotation[1][1]))
+    };
+    EulerAngles {
+        roll: roll.to_degrees(),
+        pitch: pitch.to_degrees(),
+        yaw: yaw.to_degrees(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// R = Rz(yaw) * Ry(pitch) * Rx(roll) [deg]
+    fn rotation(roll: f64, pitch: f64, yaw: f64) -> [[f64; 3]; 3] {
+        let (sr, cr) = roll.to_radians().sin_cos();
+        let (sp, cp) = pitch.to_radians().sin_cos();
+        let (sy, cy) = yaw.to_radians().sin_cos();
+        let rx = [[1.0, 0.0, 0.0], [0.0, cr, -sr], [0.0, sr, cr]];
+        let ry = [[cp, 0.0, sp], [0.0, 1.0, 0.0], [-sp, 0.0, cp]];
+        let rz = [[cy, -sy, 0.0], [sy, cy, 0.0], [0.0, 0.0, 1.0]];
+        multiply(&rz, &multiply(&ry, &rx))
+    }
+
+    fn multiply(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+        let mut result = [[0.0; 3]; 3];
+        for (i, row) in result.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+            }
+        }
+        result
+    }
+
+    fn assert_angles(angles: EulerAngles, roll: f64, pitch: f64, yaw: f64) {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(
+            close(angles.roll, roll) && close(angles.pitch, pitch) && close(angles.yaw, yaw),
+            "{:?} != (roll {}, pitch {}, yaw {})",
+            angles,
+            roll,
+            pitch,
+            yaw
+        );
+    }
+
+    fn assert_same_rotation(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) {
+        for (row_a, row_b) in a.iter().zip(b) {
+            for (value_a, value_b) in row_a.iter().zip(row_b) {
+                assert!((value_a - value_b).abs() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn identity_has_no_rotation() {
+        let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        assert_angles(euler_angles(&identity), 0.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn single_axis_rotations_of_90_degrees() {
+        // X 軸回り: Y -> Z
+        assert_angles(euler_angles(&[[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]]), 90.0, 0.0, 0.0);
+        // Z 軸回り: X -> Y
+        assert_angles(euler_angles(&[[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]), 0.0, 0.0, 90.0);
+        // Y 軸回り: Z -> X (pitch = 90° なのでジンバルロックの分岐になる)
+        assert_angles(euler_angles(&[[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]]), 0.0, 90.0, 0.0);
+    }
+
+    #[test]
+    fn general_rotation_round_trips() {
+        assert_angles(euler_angles(&rotation(10.0, 20.0, 30.0)), 10.0, 20.0, 30.0);
+        assert_angles(euler_angles(&rotation(-120.0, -45.0, 170.0)), -120.0, -45.0, 170.0);
+    }
+
+    #[test]
+    fn gimbal_lock_sets_roll_to_zero_and_keeps_the_rotation() {
+        for pitch in [90.0, -90.0] {
+            let rotation_matrix = rotation(30.0, pitch, 20.0);
+            let angles = euler_angles(&rotation_matrix);
+
+            assert_eq!(angles.roll, 0.0);
+            assert!((angles.pitch - pitch).abs() < 1e-6, "{:?}", angles);
+            // roll と yaw は区別できないため、yaw にまとめても同じ回転になる
+            assert_same_rotation(&rotation(angles.roll, angles.pitch, angles.yaw), &rotation_matrix);
+        }
+    }
+}
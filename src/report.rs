@@ -0,0 +1,262 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use opencv::{
+    core::{Mat, Size, Vector},
+    imgcodecs,
+    imgproc,
+    prelude::*,
+};
+
+use crate::{
+    camera_calibration::{CalibrationOutput, Detection, ImageReport, ImageStatus},
+    config::Config,
+    error::{CalibrationError, Result},
+    evaluation::ReprojectionStatistics,
+    visualization::Visualization,
+    COVERAGE_IMAGE_FILE_NAME, DISTORTION_MAGNITUDE_FILE_NAME, DISTORTION_RADIAL_FILE_NAME, DISTORTION_VECTORS_FILE_NAME,
+    RESIDUALS_DIR_NAME, RESIDUAL_QUIVER_FILE_NAME,
+};
+
+/// 表に載せるサムネイルの幅
+const THUMBNAIL_WIDTH: i32 = 240;
+
+/// 歪み係数の名前 (calibrate_camera が返す順序)
+const DIST_COEFF_NAMES: [&str; 14] = ["k1", "k2", "p1", "p2", "k3", "k4", "k5", "k6", "s1", "s2", "s3", "s4", "τx", "τy"];
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:1.5em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:right;vertical-align:middle}\
+th{background:#eee}td.name{text-align:left}\
+img.figure{max-width:100%;margin-bottom:1.5em}\
+pre{background:#f6f6f6;padding:1em;overflow:auto}";
+
+pub struct HtmlReport {}
+
+/// レポートに載せる1回のキャリブレーションの結果
+pub struct ReportContent<'a> {
+    pub config: &'a Config,
+    pub output: &'a CalibrationOutput,
+    /// compute_reprojection_error で求めた再投影誤差の統計
+    pub statistics: &'a ReprojectionStatistics,
+    pub detections: &'a [Detection],
+    pub image_reports: &'a [ImageReport],
+    /// 処理ごとの所要時間
+    pub timings: &'a [(&'a str, Duration)],
+}
+
+impl HtmlReport {
+    /// 画像を埋め込んだ単一の HTML ファイルとしてキャリブレーションのレポートを出力する
+    ///
+    /// 出力ディレクトリにある被覆・残差・歪みの画像を base64 で埋め込むため、
+    /// レポートだけを持ち出してもオフラインで表示できる
+    pub fn write(content: &ReportContent, output_path: &str) -> Result<()> {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>Camera calibration report</title>\n");
+        let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+        html.push_str("<h1>Camera calibration report</h1>\n");
+
+        Self::write_intrinsics(&mut html, content)?;
+        Self::write_per_view_errors(&mut html, content);
+        Self::write_figures(&mut html, content.config);
+        Self::write_failed_images(&mut html, content.image_reports);
+        Self::write_timings(&mut html, content.timings);
+
+        let config_text = toml::to_string_pretty(content.config).unwrap_or_default();
+        let _ = writeln!(html, "<h2>Effective configuration</h2>\n<pre>{}</pre>", escape_html(&config_text));
+
+        html.push_str("</body>\n</html>\n");
+        fs::write(output_path, html).map_err(|e| CalibrationError::io(output_path, e))?;
+        println!("HTML report written to: {}", output_path);
+        Ok(())
+    }
+
+    /// 内部パラメータと歪み係数を標準偏差とともに載せる
+    fn write_intrinsics(html: &mut String, content: &ReportContent) -> Result<()> {
+        let output = content.output;
+        let camera_matrix = output.camera_matrix.to_vec_2d::<f64>()?;
+        let dist_coeffs = output.dist_coeffs.data_typed::<f64>()?;
+        let std_dev = |i: usize| output.std_intrinsics.get(i).copied().unwrap_or(f64::NAN);
+
+        html.push_str("<h2>Intrinsics</h2>\n");
+        let statistics = content.statistics;
+        let _ = writeln!(
+            html,
+            "<p>Views: {} &mdash; RMS reprojection error: {:.4} px &mdash; Total error (legacy): {:.4}</p>",
+            content.detections.len(),
+            output.rms,
+            statistics.legacy_total_error
+        );
+        let _ = writeln!(
+            html,
+            "<p>RMS over all points: {:.4} px &mdash; Per-view RMS: mean {:.4} px, median {:.4} px, max {:.4} px \
+             &mdash; Max point error: {:.4} px &mdash; Failed projections: {}</p>",
+            statistics.rms,
+            statistics.mean_view_rms,
+            statistics.median_view_rms,
+            statistics.max_view_rms,
+            statistics.max_point_error,
+            statistics.failed_view_count
+        );
+
+        html.push_str("<table>\n<tr><th>Parameter</th><th>Value</th><th>Std. dev.</th></tr>\n");
+        let intrinsics = [
+            ("fx", camera_matrix[0][0]),
+            ("fy", camera_matrix[1][1]),
+            ("cx", camera_matrix[0][2]),
+            ("cy", camera_matrix[1][2]),
+        ];
+        for (i, (name, value)) in intrinsics.iter().enumerate() {
+            let _ = writeln!(
+                html,
+                "<tr><td class=\"name\">{}</td><td>{:.4}</td><td>&plusmn; {:.4}</td></tr>",
+                name,
+                value,
+                std_dev(i)
+            );
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Distortion coefficients</h2>\n");
+        html.push_str("<table>\n<tr><th>Coefficient</th><th>Value</th><th>Std. dev.</th></tr>\n");
+        for (i, (name, value)) in DIST_COEFF_NAMES.iter().zip(dist_coeffs.iter()).enumerate() {
+            let _ = writeln!(
+                html,
+                "<tr><td class=\"name\">{}</td><td>{:.6}</td><td>&plusmn; {:.6}</td></tr>",
+                name,
+                value,
+                std_dev(intrinsics.len() + i)
+            );
+        }
+        html.push_str("</table>\n");
+        Ok(())
+    }
+
+    /// ビューごとの再投影誤差をサムネイルとともに載せる
+    fn write_per_view_errors(html: &mut String, content: &ReportContent) {
+        let residuals_dir = content.config.output_path(RESIDUALS_DIR_NAME);
+
+        html.push_str("<h2>Per-view reprojection error</h2>\n");
+        html.push_str("<table>\n<tr><th>#</th><th>Image</th><th>Detector</th><th>RMS [px]</th><th>View</th></tr>\n");
+        for (i, detection) in content.detections.iter().enumerate() {
+            let error = content.output.per_view_errors.get(i).copied().unwrap_or(f64::NAN);
+
+            // 残差画像があればそれを、無ければ元画像を縮小して使う
+            let residual_path = PathBuf::from(&residuals_dir).join(Visualization::residual_image_file_name(i, detection));
+            let source_path = if residual_path.exists() {
+                residual_path
+            } else {
+                PathBuf::from(&detection.image_path)
+            };
+            let thumbnail = match thumbnail(&source_path) {
+                Ok(Some(data)) => format!("<img src=\"data:image/jpeg;base64,{}\">", data),
+                _ => "-".to_string(),
+            };
+
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"name\">{}</td><td class=\"name\">{}</td><td>{:.4}</td><td>{}</td></tr>",
+                i + 1,
+                escape_html(&detection.file_name),
+                escape_html(&detection.detector),
+                error,
+                thumbnail
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    /// 出力ディレクトリの被覆・残差・歪みの画像を埋め込む
+    fn write_figures(html: &mut String, config: &Config) {
+        let figures = [
+            ("Coverage", COVERAGE_IMAGE_FILE_NAME),
+            ("Residuals of all views", RESIDUAL_QUIVER_FILE_NAME),
+            ("Distortion magnitude", DISTORTION_MAGNITUDE_FILE_NAME),
+            ("Distortion vector field", DISTORTION_VECTORS_FILE_NAME),
+            ("Radial distortion", DISTORTION_RADIAL_FILE_NAME),
+        ];
+        for (title, file_name) in figures {
+            let Ok(bytes) = fs::read(config.output_path(file_name)) else {
+                continue;
+            };
+            let _ = writeln!(
+                html,
+                "<h2>{}</h2>\n<img class=\"figure\" src=\"data:image/jpeg;base64,{}\">",
+                title,
+                STANDARD.encode(bytes)
+            );
+        }
+    }
+
+    /// 読み込めなかった画像・パターンが見つからなかった画像・除外した画像を載せる
+    fn write_failed_images(html: &mut String, image_reports: &[ImageReport]) {
+        let failed: Vec<&ImageReport> = image_reports
+            .iter()
+            .filter(|report| report.status != ImageStatus::Detected)
+            .collect();
+
+        let _ = writeln!(html, "<h2>Images not used ({})</h2>", failed.len());
+        if failed.is_empty() {
+            return;
+        }
+        html.push_str("<table>\n<tr><th>Image</th><th>Status</th><th>Detail</th></tr>\n");
+        for report in failed {
+            let status = match report.status {
+                ImageStatus::Undecodable => "undecodable",
+                ImageStatus::PatternNotFound => "pattern not found",
+                ImageStatus::Rejected => "rejected",
+                ImageStatus::Detected => "detected",
+            };
+            let _ = writeln!(
+                html,
+                "<tr><td class=\"name\">{}</td><td class=\"name\">{}</td><td class=\"name\">{}</td></tr>",
+                escape_html(&report.image_path),
+                status,
+                escape_html(report.detail.as_deref().unwrap_or(""))
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn write_timings(html: &mut String, timings: &[(&str, Duration)]) {
+        html.push_str("<h2>Timings</h2>\n<table>\n<tr><th>Step</th><th>Time [s]</th></tr>\n");
+        for (step, duration) in timings {
+            let _ = writeln!(
+                html,
+                "<tr><td class=\"name\">{}</td><td>{:.3}</td></tr>",
+                step,
+                duration.as_secs_f64()
+            );
+        }
+        html.push_str("</table>\n");
+    }
+}
+
+/// 画像を縮小して JPEG の base64 文字列にする
+/// 読み込めない画像は None
+fn thumbnail(image_path: &Path) -> opencv::Result<Option<String>> {
+    let img = imgcodecs::imread(&image_path.to_string_lossy(), imgcodecs::IMREAD_COLOR)?;
+    if img.empty() {
+        return Ok(None);
+    }
+
+    let size = img.size()?;
+    let height = (size.height as f64 * THUMBNAIL_WIDTH as f64 / size.width.max(1) as f64).round() as i32;
+    let mut small = Mat::default();
+    imgproc::resize(&img, &mut small, Size::new(THUMBNAIL_WIDTH, height.max(1)), 0.0, 0.0, imgproc::INTER_AREA)?;
+
+    let mut buffer = Vector::<u8>::new();
+    imgcodecs::imencode(".jpeg", &small, &mut buffer, &Vector::new())?;
+    Ok(Some(STANDARD.encode(buffer.as_slice())))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,373 @@
+use clap::ValueEnum;
+use opencv::{
+    core::{Point3f, Size, Vector},
+    objdetect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    command_line::CalibrationPattern,
+    error::{CalibrationError, Result},
+    file::CustomFile,
+};
+
+/// キャリブレーションターゲットの記述
+///
+/// `spacing` の意味はパターンによって異なる
+/// - chessboard: 正方形の一辺 (`rows` / `cols` は内側のコーナー数)
+/// - symmetric / asymmetric: 円の中心間隔 (`rows` / `cols` は円の数)
+/// - charuco: 正方形の一辺 (`rows` / `cols` は正方形の数)
+/// - aprilgrid: タグの一辺 (`rows` / `cols` はタグの数)
+///
+/// `units` を省略した場合、物体座標は `spacing` をそのまま単位とする
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TargetDescription {
+    #[serde(rename = "type")]
+    pub pattern: CalibrationPattern,
+    pub rows: i32,
+    pub cols: i32,
+    pub spacing: f64,
+    /// ChArUco のマーカーの一辺
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marker_size: Option<f64>,
+    /// AprilGrid のタグ間隔 (タグの一辺に対する比率, Kalibr の tagSpacing)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_spacing: Option<f64>,
+    /// マーカーの辞書名 (例: DICT_5X5_1000, DICT_APRILTAG_36h11)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub units: Option<Units>,
+}
+
+impl TargetDescription {
+    /// ターゲット記述ファイル (JSON) を読み込み、検証する
+    pub fn load(file_path: &str) -> Result<Self> {
+        let target: Self = CustomFile::read_json(file_path)?;
+
+        target.validate().map_err(|e| {
+            CalibrationError::Config(format!("invalid target description '{}': {}", file_path, e))
+        })?;
+
+        Ok(target)
+    }
+
+    /// 値の整合性を検証する
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let min_size = match self.pattern {
+            CalibrationPattern::ChessBoard => 2,
+            CalibrationPattern::ChArUco => 3,
+            _ => 1,
+        };
+        if self.rows < min_size || self.cols < min_size {
+            return Err(format!(
+                "rows and cols must be at least {} for a {} target (got rows = {}, cols = {})",
+                min_size, self.pattern_name(), self.rows, self.cols
+            ));
+        }
+        if !(self.spacing.is_finite() && self.spacing > 0.0) {
+            return Err(format!("spacing must be a positive number (got {})", self.spacing));
+        }
+
+        match (self.pattern, self.marker_size) {
+            (CalibrationPattern::ChArUco, None) => return Err("marker_size is required for a charuco target".to_string()),
+            (CalibrationPattern::ChArUco, Some(marker_size)) if !(marker_size > 0.0 && marker_size < self.spacing) => {
+                return Err(format!(
+                    "marker_size must be positive and smaller than spacing (got marker_size = {}, spacing = {})",
+                    marker_size, self.spacing
+                ));
+            }
+            (CalibrationPattern::ChArUco, Some(_)) | (_, None) => {}
+            (_, Some(_)) => return Err(format!("marker_size is not used by a {} target", self.pattern_name())),
+        }
+
+        match (self.pattern, self.tag_spacing) {
+            (CalibrationPattern::AprilGrid, None) => return Err("tag_spacing is required for an aprilgrid target".to_string()),
+            (CalibrationPattern::AprilGrid, Some(tag_spacing)) if !(tag_spacing.is_finite() && tag_spacing > 0.0) => {
+                return Err(format!("tag_spacing must be a positive number (got {})", tag_spacing));
+            }
+            (CalibrationPattern::AprilGrid, Some(_)) | (_, None) => {}
+            (_, Some(_)) => return Err(format!("tag_spacing is not used by a {} target", self.pattern_name())),
+        }
+
+        match (self.pattern, &self.dictionary) {
+            (CalibrationPattern::ChArUco, None) => return Err("dictionary is required for a charuco target".to_string()),
+            (CalibrationPattern::ChArUco | CalibrationPattern::AprilGrid, Some(name)) => {
+                if !DICTIONARIES.iter().any(|(dictionary_name, _)| dictionary_name.eq_ignore_ascii_case(name)) {
+                    return Err(format!("unknown marker dictionary '{}'", name));
+                }
+            }
+            (_, None) => {}
+            (_, Some(_)) => return Err(format!("dictionary is not used by a {} target", self.pattern_name())),
+        }
+
+        Ok(())
+    }
+
+    /// パターンの種類をファイル上の名前で返す
+    pub fn pattern_name(&self) -> &'static str {
+        match self.pattern {
+            CalibrationPattern::ChessBoard => "chessboard",
+            CalibrationPattern::SymmetricCircleGrid => "symmetric",
+            CalibrationPattern::AsymmetricCircleGrid => "asymmetric",
+            CalibrationPattern::ChArUco => "charuco",
+            CalibrationPattern::AprilGrid => "aprilgrid",
+        }
+    }
+
+    /// 指定したパターンのターゲットであることを確認する
+    pub fn expect_pattern(&self, patterns: &[CalibrationPattern]) -> Result<()> {
+        if patterns.contains(&self.pattern) {
+            return Ok(());
+        }
+
+        Err(CalibrationError::Config(format!(
+            "the target description is a {} target, which cannot be used here",
+            self.pattern_name()
+        )))
+    }
+
+    /// 検出関数に渡すパターンサイズ (cols, rows)
+    pub fn pattern_size(&self) -> Size {
+        Size::new(self.cols, self.rows)
+    }
+
+    /// ボード全体が見えている場合の物体座標
+    pub fn object_points(&self) -> Vector<Point3f> {
+        let spacing = self.spacing as f32;
+        let mut objp = Vector::<Point3f>::new();
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                let x = match self.pattern {
+                    // OpenCV の非対称円グリッドの配置
+                    CalibrationPattern::AsymmetricCircleGrid => (2 * j + i % 2) as f32,
+                    _ => j as f32,
+                };
+                objp.push(Point3f::new(x * spacing, i as f32 * spacing, 0.0));
+            }
+        }
+        objp
+    }
+
+    /// AprilGrid のタグIDから4隅の物体座標を求める
+    /// Kalibr と同様にタグ0を左下とし、行は上方向に増える。
+    /// 返す順序は ArucoDetector が返すコーナーの順序 (左上, 右上, 右下, 左下) に合わせる
+    pub fn aprilgrid_tag_corners(&self, tag_id: i32) -> Option<[Point3f; 4]> {
+        if tag_id < 0 || tag_id >= self.rows * self.cols {
+            return None;
+        }
+
+        let tag_size = self.spacing as f32;
+        let pitch = tag_size * (1.0 + self.tag_spacing.unwrap_or_default() as f32);
+        let x = (tag_id % self.cols) as f32 * pitch;
+        let y = (tag_id / self.cols) as f32 * pitch;
+
+        Some([
+            Point3f::new(x, y + tag_size, 0.0),
+            Point3f::new(x + tag_size, y + tag_size, 0.0),
+            Point3f::new(x + tag_size, y, 0.0),
+            Point3f::new(x, y, 0.0),
+        ])
+    }
+
+    /// マーカー辞書を取得する
+    pub fn marker_dictionary(&self, default_name: &str) -> Result<objdetect::Dictionary> {
+        let name = self.dictionary.as_deref().unwrap_or(default_name);
+        match predefined_dictionary(name) {
+            Some(dictionary) => Ok(dictionary?),
+            None => Err(CalibrationError::Config(format!("unknown marker dictionary '{}'", name))),
+        }
+    }
+}
+
+/// ターゲットの長さの単位
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    Mm,
+    Cm,
+    M,
+    In,
+}
+
+impl Units {
+    /// 1単位あたりのミリメートル
+    pub fn to_mm(&self) -> f64 {
+        match self {
+            Units::Mm => 1.0,
+            Units::Cm => 10.0,
+            Units::M => 1000.0,
+            Units::In => 25.4,
+        }
+    }
+}
+
+/// マーカー辞書の名前と OpenCV の定義済み辞書の対応
+const DICTIONARIES: [(&str, i32); 21] = [
+    ("DICT_4X4_50", objdetect::DICT_4X4_50),
+    ("DICT_4X4_100", objdetect::DICT_4X4_100),
+    ("DICT_4X4_250", objdetect::DICT_4X4_250),
+    ("DICT_4X4_1000", objdetect::DICT_4X4_1000),
+    ("DICT_5X5_50", objdetect::DICT_5X5_50),
+    ("DICT_5X5_100", objdetect::DICT_5X5_100),
+    ("DICT_5X5_250", objdetect::DICT_5X5_250),
+    ("DICT_5X5_1000", objdetect::DICT_5X5_1000),
+    ("DICT_6X6_50", objdetect::DICT_6X6_50),
+    ("DICT_6X6_100", objdetect::DICT_6X6_100),
+    ("DICT_6X6_250", objdetect::DICT_6X6_250),
+    ("DICT_6X6_1000", objdetect::DICT_6X6_1000),
+    ("DICT_7X7_50", objdetect::DICT_7X7_50),
+    ("DICT_7X7_100", objdetect::DICT_7X7_100),
+    ("DICT_7X7_250", objdetect::DICT_7X7_250),
+    ("DICT_7X7_1000", objdetect::DICT_7X7_1000),
+    ("DICT_ARUCO_ORIGINAL", objdetect::DICT_ARUCO_ORIGINAL),
+    ("DICT_APRILTAG_16h5", objdetect::DICT_APRILTAG_16h5),
+    ("DICT_APRILTAG_25h9", objdetect::DICT_APRILTAG_25h9),
+    ("DICT_APRILTAG_36h10", objdetect::DICT_APRILTAG_36h10),
+    ("DICT_APRILTAG_36h11", objdetect::DICT_APRILTAG_36h11),
+];
+
+/// 辞書名から OpenCV の定義済み辞書を取得する
+fn predefined_dictionary(name: &str) -> Option<opencv::Result<objdetect::Dictionary>> {
+    DICTIONARIES
+        .iter()
+        .find(|(dictionary_name, _)| dictionary_name.eq_ignore_ascii_case(name))
+        .map(|(_, dictionary)| objdetect::get_predefined_dictionary_i32(*dictionary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(pattern: CalibrationPattern, rows: i32, cols: i32, spacing: f64) -> TargetDescription {
+        TargetDescription {
+            pattern,
+            rows,
+            cols,
+            spacing,
+            marker_size: None,
+            tag_spacing: None,
+            dictionary: None,
+            units: None,
+        }
+    }
+
+    fn aprilgrid(rows: i32, cols: i32, spacing: f64, tag_spacing: f64) -> TargetDescription {
+        TargetDescription {
+            tag_spacing: Some(tag_spacing),
+            ..target(CalibrationPattern::AprilGrid, rows, cols, spacing)
+        }
+    }
+
+    fn coordinates(points: impl IntoIterator<Item = Point3f>) -> Vec<(f32, f32, f32)> {
+        points.into_iter().map(|p| (p.x, p.y, p.z)).collect()
+    }
+
+    #[test]
+    fn chessboard_points_are_a_row_major_grid() {
+        let points = target(CalibrationPattern::ChessBoard, 2, 3, 25.0).object_points();
+        assert_eq!(
+            coordinates(points),
+            [
+                (0.0, 0.0, 0.0),
+                (25.0, 0.0, 0.0),
+                (50.0, 0.0, 0.0),
+                (0.0, 25.0, 0.0),
+                (25.0, 25.0, 0.0),
+                (50.0, 25.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn asymmetric_circle_grid_shifts_odd_rows() {
+        // x = (2j + i % 2) * spacing, y = i * spacing
+        let points = target(CalibrationPattern::AsymmetricCircleGrid, 3, 2, 10.0).object_points();
+        assert_eq!(
+            coordinates(points),
+            [
+                (0.0, 0.0, 0.0),
+                (20.0, 0.0, 0.0),
+                (10.0, 10.0, 0.0),
+                (30.0, 10.0, 0.0),
+                (0.0, 20.0, 0.0),
+                (20.0, 20.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn aprilgrid_tag_zero_is_at_the_origin() {
+        let grid = aprilgrid(2, 3, 10.0, 0.5);
+        // 左上, 右上, 右下, 左下 (y は上方向)
+        assert_eq!(
+            coordinates(grid.aprilgrid_tag_corners(0).unwrap()),
+            [(0.0, 10.0, 0.0), (10.0, 10.0, 0.0), (10.0, 0.0, 0.0), (0.0, 0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn aprilgrid_tags_are_placed_by_column_then_row() {
+        // タグの間隔は spacing * (1 + tag_spacing) = 15
+        let grid = aprilgrid(2, 3, 10.0, 0.5);
+        assert_eq!(
+            coordinates(grid.aprilgrid_tag_corners(2).unwrap()),
+            [(30.0, 10.0, 0.0), (40.0, 10.0, 0.0), (40.0, 0.0, 0.0), (30.0, 0.0, 0.0)]
+        );
+        assert_eq!(
+            coordinates(grid.aprilgrid_tag_corners(4).unwrap()),
+            [(15.0, 25.0, 0.0), (25.0, 25.0, 0.0), (25.0, 15.0, 0.0), (15.0, 15.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn aprilgrid_rejects_tag_ids_outside_the_grid() {
+        let grid = aprilgrid(2, 3, 10.0, 0.5);
+        assert!(grid.aprilgrid_tag_corners(-1).is_none());
+        assert!(grid.aprilgrid_tag_corners(6).is_none());
+    }
+
+    #[test]
+    fn valid_targets_pass() {
+        target(CalibrationPattern::ChessBoard, 6, 9, 25.0).validate().unwrap();
+        target(CalibrationPattern::AsymmetricCircleGrid, 11, 4, 20.0).validate().unwrap();
+        aprilgrid(6, 6, 0.088, 0.3).validate().unwrap();
+        TargetDescription {
+            marker_size: Some(22.0),
+            dictionary: Some("DICT_5X5_100".to_string()),
+            ..target(CalibrationPattern::ChArUco, 7, 5, 30.0)
+        }
+        .validate()
+        .unwrap();
+    }
+
+    #[test]
+    fn too_few_rows_or_cols_are_rejected() {
+        assert!(target(CalibrationPattern::ChessBoard, 1, 9, 25.0).validate().is_err());
+        assert!(target(CalibrationPattern::ChessBoard, 6, 0, 25.0).validate().is_err());
+        assert!(target(CalibrationPattern::SymmetricCircleGrid, -3, 4, 20.0).validate().is_err());
+        let charuco = TargetDescription {
+            marker_size: Some(22.0),
+            dictionary: Some("DICT_5X5_100".to_string()),
+            ..target(CalibrationPattern::ChArUco, 2, 5, 30.0)
+        };
+        assert!(charuco.validate().is_err());
+    }
+
+    #[test]
+    fn non_positive_or_non_finite_spacing_is_rejected() {
+        for spacing in [0.0, -25.0, f64::NAN, f64::INFINITY] {
+            assert!(target(CalibrationPattern::ChessBoard, 6, 9, spacing).validate().is_err(), "{}", spacing);
+        }
+        assert!(aprilgrid(6, 6, 0.088, 0.0).validate().is_err());
+    }
+
+    #[test]
+    fn fields_of_other_patterns_are_rejected() {
+        let chessboard = target(CalibrationPattern::ChessBoard, 6, 9, 25.0);
+        assert!(TargetDescription { marker_size: Some(10.0), ..chessboard.clone() }.validate().is_err());
+        assert!(TargetDescription { tag_spacing: Some(0.3), ..chessboard.clone() }.validate().is_err());
+        assert!(TargetDescription { dictionary: Some("DICT_5X5_100".to_string()), ..chessboard }.validate().is_err());
+        assert!(target(CalibrationPattern::AprilGrid, 6, 6, 0.088).validate().is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use opencv::{
+    core::{self, Mat, Point, Rect, Scalar, Vector},
+    imgcodecs,
+    imgproc,
+    objdetect,
+    prelude::*,
+};
+
+use crate::{
+    command_line::CalibrationPattern,
+    error::{CalibrationError, Result},
+    file::CustomFile,
+    target::TargetDescription,
+};
+
+// PDF で円を4本のベジェ曲線で近似する際の係数
+const BEZIER_CIRCLE_KAPPA: f64 = 0.552_284_749_8;
+
+/// 描画する図形 (ターゲットの単位系, 原点は左上)
+enum Shape {
+    Rect { x: f64, y: f64, width: f64, height: f64 },
+    Circle { cx: f64, cy: f64, radius: f64 },
+}
+
+/// 白地に黒で描画するターゲットのレイアウト
+struct Layout {
+    width: f64,
+    height: f64,
+    shapes: Vec<Shape>,
+}
+
+pub struct TargetGenerator {}
+
+impl TargetGenerator {
+    /// ターゲットを SVG / PNG / PDF と記述ファイル (JSON) に出力する
+    pub fn generate(target: &TargetDescription, margin: f64, dpi: f64, output_path: &str) -> Result<()> {
+        target
+            .validate()
+            .map_err(|e| CalibrationError::Config(format!("invalid target: {}", e)))?;
+        let unit_to_mm = match target.units {
+            Some(units) => units.to_mm(),
+            None => return Err(CalibrationError::Config("units are required to generate a target".to_string())),
+        };
+
+        let layout = Self::layout(target, margin)?;
+        let base_path = PathBuf::from(output_path);
+        if let Some(dir_path) = base_path.parent() {
+            fs::create_dir_all(dir_path).map_err(|e| CalibrationError::io(dir_path.to_string_lossy(), e))?;
+        }
+
+        let svg_path = base_path.with_extension("svg");
+        fs::write(&svg_path, Self::render_svg(&layout, unit_to_mm))
+            .map_err(|e| CalibrationError::io(svg_path.to_string_lossy(), e))?;
+        println!("Target written to: {}", svg_path.display());
+
+        let pdf_path = base_path.with_extension("pdf");
+        fs::write(&pdf_path, Self::render_pdf(&layout, unit_to_mm))
+            .map_err(|e| CalibrationError::io(pdf_path.to_string_lossy(), e))?;
+        println!("Target written to: {}", pdf_path.display());
+
+        let png_path = base_path.with_extension("png");
+        let png = Self::render_png(&layout, unit_to_mm / 25.4 * dpi)?;
+        imgcodecs::imwrite(&png_path.to_string_lossy(), &png, &Vector::new())?;
+        println!("Target written to: {}", png_path.display());
+
+        CustomFile::write_json(&base_path.with_extension("json").to_string_lossy(), target)
+    }
+
+    /// パターンごとに図形を配置する
+    fn layout(target: &TargetDescription, margin: f64) -> Result<Layout> {
+        let s = target.spacing;
+        let rows = target.rows;
+        let cols = target.cols;
+        let mut shapes = Vec::new();
+
+        let (content_width, content_height) = match target.pattern {
+            CalibrationPattern::ChessBoard => {
+                // 内側のコーナー数 + 1 が正方形の数
+                for r in 0..=rows {
+                    for c in 0..=cols {
+                        if (r + c) % 2 == 0 {
+                            shapes.push(Shape::Rect { x: margin + c as f64 * s, y: margin + r as f64 * s, width: s, height: s });
+                        }
+                    }
+                }
+                ((cols + 1) as f64 * s, (rows + 1) as f64 * s)
+            }
+            CalibrationPattern::SymmetricCircleGrid => {
+                for r in 0..rows {
+                    for c in 0..cols {
+                        shapes.push(Shape::Circle { cx: margin + (c + 1) as f64 * s, cy: margin + (r + 1) as f64 * s, radius: s / 5.0 });
+                    }
+                }
+                ((cols + 1) as f64 * s, (rows + 1) as f64 * s)
+            }
+            CalibrationPattern::AsymmetricCircleGrid => {
+                // OpenCV の非対称円グリッドと同じ配置 ((2 * c + r % 2) * s, r * s)
+                for r in 0..rows {
+                    for c in 0..cols {
+                        let x = (2 * c + r % 2) as f64 * s;
+                        shapes.push(Shape::Circle { cx: margin + s + x, cy: margin + (r + 1) as f64 * s, radius: s / 5.0 });
+                    }
+                }
+                ((2 * cols + 1) as f64 * s, (rows + 1) as f64 * s)
+            }
+            CalibrationPattern::ChArUco => {
+                let marker_size = target.marker_size.unwrap_or(s * 0.7);
+                let dictionary = target.marker_dictionary("DICT_5X5_100")?;
+                // OpenCV 4.6 以降の ChArUco と同様に左上は黒, マーカーは白い正方形に左上から順に配置
+                let mut marker_id = 0;
+                for r in 0..rows {
+                    for c in 0..cols {
+                        let x = margin + c as f64 * s;
+                        let y = margin + r as f64 * s;
+                        if (r + c) % 2 == 0 {
+                            shapes.push(Shape::Rect { x, y, width: s, height: s });
+                        } else {
+                            let offset = (s - marker_size) / 2.0;
+                            Self::push_marker(&mut shapes, &dictionary, marker_id, x + offset, y + offset, marker_size)?;
+                            marker_id += 1;
+                        }
+                    }
+                }
+                (cols as f64 * s, rows as f64 * s)
+            }
+            CalibrationPattern::AprilGrid => {
+                // Kalibr の AprilGrid: タグ0が左下, タグ間の交点に黒い正方形
+                let gap = s * target.tag_spacing.unwrap_or(0.3);
+                let pitch = s + gap;
+                let dictionary = target.marker_dictionary("DICT_APRILTAG_36h11")?;
+                let content_width = cols as f64 * pitch + gap;
+                let content_height = rows as f64 * pitch + gap;
+                for r in 0..=rows {
+                    for c in 0..=cols {
+                        shapes.push(Shape::Rect { x: margin + c as f64 * pitch, y: margin + r as f64 * pitch, width: gap, height: gap });
+                    }
+                }
+                for r in 0..rows {
+                    for c in 0..cols {
+                        let x = margin + gap + c as f64 * pitch;
+                        let y = margin + content_height - (r + 1) as f64 * pitch;
+                        Self::push_marker(&mut shapes, &dictionary, r * cols + c, x, y, s)?;
+                    }
+                }
+                (content_width, content_height)
+            }
+        };
+
+        Ok(Layout {
+            width: content_width + 2.0 * margin,
+            height: content_height + 2.0 * margin,
+            shapes,
+        })
+    }
+
+    /// マーカーの黒いセルを図形として追加する (境界は1ビット)
+    fn push_marker(
+        shapes: &mut Vec<Shape>,
+        dictionary: &objdetect::Dictionary,
+        marker_id: i32,
+        x: f64,
+        y: f64,
+        size: f64,
+    ) -> Result<()> {
+        if marker_id >= dictionary.bytes_list().rows() {
+            return Err(CalibrationError::Config(format!(
+                "the marker dictionary has no marker with id {}",
+                marker_id
+            )));
+        }
+
+        // 1セル = 1ピクセルで生成してビットを読み取る
+        let cells = dictionary.marker_size() + 2;
+        let mut bits = Mat::default();
+        objdetect::generate_image_marker(dictionary, marker_id, cells, &mut bits, 1)?;
+
+        let cell_size = size / cells as f64;
+        for r in 0..cells {
+            for c in 0..cells {
+                if *bits.at_2d::<u8>(r, c)? < 128 {
+                    shapes.push(Shape::Rect {
+                        x: x + c as f64 * cell_size,
+                        y: y + r as f64 * cell_size,
+                        width: cell_size,
+                        height: cell_size,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_svg(layout: &Layout, unit_to_mm: f64) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}mm" height="{}mm" viewBox="0 0 {} {}">"#,
+            layout.width * unit_to_mm,
+            layout.height * unit_to_mm,
+            layout.width,
+            layout.height
+        );
+        let _ = writeln!(svg, r#"<rect x="0" y="0" width="{}" height="{}" fill="white"/>"#, layout.width, layout.height);
+        for shape in &layout.shapes {
+            let _ = match shape {
+                Shape::Rect { x, y, width, height } => writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="black" shape-rendering="crispEdges"/>"#,
+                    x, y, width, height
+                ),
+                Shape::Circle { cx, cy, radius } => {
+                    writeln!(svg, r#"<circle cx="{}" cy="{}" r="{}" fill="black"/>"#, cx, cy, radius)
+                }
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn render_png(layout: &Layout, pixels_per_unit: f64) -> opencv::Result<Mat> {
+        let to_px = |v: f64| (v * pixels_per_unit).round() as i32;
+        let black = Scalar::all(0.0);
+        let mut img = Mat::new_rows_cols_with_default(
+            to_px(layout.height),
+            to_px(layout.width),
+            core::CV_8UC1,
+            Scalar::all(255.0),
+        )?;
+
+        // 円はサブピクセル精度で描画する
+        const SHIFT: i32 = 4;
+        let to_subpx = |v: f64| (v * pixels_per_unit * (1 << SHIFT) as f64).round() as i32;
+
+        for shape in &layout.shapes {
+            match shape {
+                Shape::Rect { x, y, width, height } => {
+                    let x0 = to_px(*x);
+                    let y0 = to_px(*y);
+                    let rect = Rect::new(x0, y0, to_px(x + width) - x0, to_px(y + height) - y0);
+                    imgproc::rectangle(&mut img, rect, black, imgproc::FILLED, imgproc::LINE_8, 0)?;
+                }
+                Shape::Circle { cx, cy, radius } => {
+                    let center = Point::new(to_subpx(*cx), to_subpx(*cy));
+                    imgproc::circle(&mut img, center, to_subpx(*radius), black, imgproc::FILLED, imgproc::LINE_AA, SHIFT)?;
+                }
+            }
+        }
+
+        Ok(img)
+    }
+
+    fn render_pdf(layout: &Layout, unit_to_mm: f64) -> Vec<u8> {
+        let points_per_unit = unit_to_mm / 25.4 * 72.0;
+        let page_width = layout.width * points_per_unit;
+        let page_height = layout.height * points_per_unit;
+
+        // ターゲットの単位系 (原点は左上, y軸は下向き) から PDF の座標系へ変換する
+        let mut content = String::new();
+        let _ = writeln!(content, "{} 0 0 {} 0 {} cm", points_per_unit, -points_per_unit, page_height);
+        content.push_str("0 g\n");
+        for shape in &layout.shapes {
+            match shape {
+                Shape::Rect { x, y, width, height } => {
+                    let _ = writeln!(content, "{} {} {} {} re f", x, y, width, height);
+                }
+                Shape::Circle { cx, cy, radius } => {
+                    let k = radius * BEZIER_CIRCLE_KAPPA;
+                    let _ = writeln!(content, "{} {} m", cx + radius, cy);
+                    let _ = writeln!(content, "{} {} {} {} {} {} c", cx + radius, cy + k, cx + k, cy + radius, cx, cy + radius);
+                    let _ = writeln!(content, "{} {} {} {} {} {} c", cx - k, cy + radius, cx - radius, cy + k, cx - radius, cy);
+                    let _ = writeln!(content, "{} {} {} {} {} {} c", cx - radius, cy - k, cx - k, cy - radius, cx, cy - radius);
+                    let _ = writeln!(content, "{} {} {} {} {} {} c f", cx + k, cy - radius, cx + radius, cy - k, cx + radius, cy);
+                }
+            }
+        }
+
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R >>",
+                page_width, page_height
+            ),
+            format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+        ];
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+
+        let xref_offset = pdf.len();
+        let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            pdf,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        );
+
+        pdf.into_bytes()
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+
+use opencv::{
+    core::{self, Mat, Size, Vector},
+    imgcodecs,
+    imgproc,
+    prelude::*,
+    videoio,
+};
+use serde::Serialize;
+
+use crate::{
+    config::VideoConfig,
+    error::{CalibrationError, Result},
+    file::CustomFile,
+};
+
+/// 比較用に縮小するフレームの幅
+const NOVELTY_FRAME_WIDTH: i32 = 320;
+
+pub struct VideoSampler {}
+
+/// フレームごとの選別結果
+#[derive(Serialize)]
+struct FrameReport {
+    frame_index: usize,
+    sharpness: f64,
+    novelty: Option<f64>,
+    selected: bool,
+    /// 選ばれなかった理由
+    rejected_by: Option<&'static str>,
+    file_name: Option<String>,
+}
+
+impl VideoSampler {
+    /// 動画からキャリブレーションに使うフレームを選び、画像として保存する
+    ///
+    /// `frame_step` ごとにフレームを取り出し、ぼけているフレーム (ラプラシアンの分散が小さい) と
+    /// 直前に選んだフレームとの差が小さいフレームを除外する。
+    /// 選別の結果は `report_path` に書き出す
+    pub fn extract_frames(
+        video_path: &str,
+        video_config: &VideoConfig,
+        frames_dir: &str,
+        report_path: &str,
+    ) -> Result<Vec<PathBuf>> {
+        let mut capture = videoio::VideoCapture::from_file(video_path, videoio::CAP_ANY)?;
+        if !capture.is_opened()? {
+            return Err(CalibrationError::Io {
+                path: video_path.to_string(),
+                source: std::io::Error::new(std::io::ErrorKind::InvalidData, "failed to open the video"),
+            });
+        }
+        let frame_count = capture.get(videoio::CAP_PROP_FRAME_COUNT)?;
+        println!("Reading video: {} ({} frames)", video_path, frame_count);
+
+        CustomFile::create_out_dir(frames_dir)?;
+
+        let frame_step = video_config.frame_step.max(1);
+        let mut frame_paths = Vec::new();
+        let mut frame_reports = Vec::new();
+        let mut last_selected: Option<Mat> = None;
+        let mut frame = Mat::default();
+        let mut frame_index = 0;
+
+        while capture.read(&mut frame)? {
+            if frame.empty() {
+                break;
+            }
+            if frame_index % frame_step != 0 {
+                frame_index += 1;
+                continue;
+            }
+            if video_config.max_frames.is_some_and(|max_frames| frame_paths.len() >= max_frames) {
+                break;
+            }
+
+            let mut gray = Mat::default();
+            imgproc::cvt_color_def(&frame, &mut gray, imgproc::COLOR_BGR2GRAY)?;
+            let sharpness = sharpness(&gray)?;
+            let small = shrink(&gray)?;
+            let novelty = match &last_selected {
+                Some(last) => Some(novelty(&small, last)?),
+                None => None,
+            };
+
+            let rejected_by = if sharpness < video_config.min_sharpness {
+                Some("blur")
+            } else if novelty.is_some_and(|novelty| novelty < video_config.min_novelty) {
+                Some("novelty")
+            } else {
+                None
+            };
+
+            let file_name = match rejected_by {
+                None => {
+                    let file_name = format!("frame_{:06}.png", frame_index);
+                    let frame_path = PathBuf::from(frames_dir).join(&file_name);
+                    imgcodecs::imwrite(&frame_path.to_string_lossy(), &frame, &Vector::new())?;
+                    frame_paths.push(frame_path);
+                    last_selected = Some(small);
+                    Some(file_name)
+                }
+                Some(_) => None,
+            };
+
+            frame_reports.push(FrameReport {
+                frame_index,
+                sharpness,
+                novelty,
+                selected: rejected_by.is_none(),
+                rejected_by,
+                file_name,
+            });
+            frame_index += 1;
+        }
+
+        let blurry_cnt = frame_reports.iter().filter(|r| r.rejected_by == Some("blur")).count();
+        let similar_cnt = frame_reports.iter().filter(|r| r.rejected_by == Some("novelty")).count();
+        println!(
+            "Selected {} of {} sampled frames (blurry: {}, too similar: {})",
+            frame_paths.len(),
+            frame_reports.len(),
+            blurry_cnt,
+            similar_cnt
+        );
+
+        let _ = CustomFile::write_json(report_path, &frame_reports);
+
+        Ok(frame_paths)
+    }
+}
+
+/// ラプラシアンの分散 (大きいほど鮮明)
+fn sharpness(gray: &Mat) -> opencv::Result<f64> {
+    let mut laplacian = Mat::default();
+    imgproc::laplacian(gray, &mut laplacian, core::CV_64F, 3, 1.0, 0.0, core::BORDER_DEFAULT)?;
+
+    let mut mean = Vector::<f64>::new();
+    let mut stddev = Vector::<f64>::new();
+    core::mean_std_dev(&laplacian, &mut mean, &mut stddev, &core::no_array())?;
+    let stddev = stddev.get(0)?;
+    Ok(stddev * stddev)
+}
+
+/// 比較用にフレームを縮小する
+fn shrink(gray: &Mat) -> opencv::Result<Mat> {
+    let size = gray.size()?;
+    let height = (size.height as f64 * NOVELTY_FRAME_WIDTH as f64 / size.width.max(1) as f64).round() as i32;
+    let mut small = Mat::default();
+    imgproc::resize(gray, &mut small, Size::new(NOVELTY_FRAME_WIDTH, height.max(1)), 0.0, 0.0, imgproc::INTER_AREA)?;
+    Ok(small)
+}
+
+/// 直前に選んだフレームとの平均輝度差 (0 - 255)
+fn novelty(small: &Mat, last_selected: &Mat) -> opencv::Result<f64> {
+    let mut diff = Mat::default();
+    core::absdiff(small, last_selected, &mut diff)?;
+    Ok(core::mean(&diff, &core::no_array())?[0])
+}
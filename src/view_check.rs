@@ -0,0 +1,201 @@
+use opencv::{
+    calib3d,
+    core::{Mat, Point2f, Point3f, Size, Vector},
+    prelude::*,
+};
+
+use crate::{
+    config::CheckConfig,
+    error::{CalibrationError, Result},
+};
+
+pub struct ViewCheck {}
+
+/// 1項目のチェック結果
+struct Finding {
+    name: &'static str,
+    passed: bool,
+    summary: String,
+    /// 不足している場合に追加で撮影すべきビューの説明
+    suggestion: String,
+}
+
+impl ViewCheck {
+    /// キャリブレーション前にビューの数・画像の被覆・ボードの傾きと距離の範囲を確認する
+    ///
+    /// ビュー数が足りない場合は常にエラー、それ以外は `strict` の場合のみエラーとし、
+    /// そうでなければ警告として追加で撮影すべきビューを表示する
+    pub fn run(
+        obj_points: &Vector<Vector<Point3f>>,
+        img_points: &Vector<Vector<Point2f>>,
+        frame_size: Size,
+        check_config: &CheckConfig,
+    ) -> Result<()> {
+        if !check_config.enabled {
+            return Ok(());
+        }
+
+        let view_cnt = obj_points.len();
+        if view_cnt < check_config.min_views {
+            return Err(CalibrationError::Calibration(format!(
+                "only {} views with a detected pattern (min_views = {}). Capture at least {} more views",
+                view_cnt,
+                check_config.min_views,
+                check_config.min_views - view_cnt
+            )));
+        }
+
+        let mut findings = vec![Self::check_coverage(img_points, frame_size, check_config)?];
+        findings.extend(Self::check_poses(obj_points, img_points, frame_size, check_config)?);
+
+        println!("Pre-calibration checks ({} views):", view_cnt);
+        for finding in &findings {
+            let status = if finding.passed { "OK  " } else { "WARN" };
+            println!("  [{}] {}: {}", status, finding.name, finding.summary);
+        }
+
+        let failed: Vec<&Finding> = findings.iter().filter(|finding| !finding.passed).collect();
+        if failed.is_empty() {
+            return Ok(());
+        }
+
+        println!("Additional views to capture:");
+        for finding in &failed {
+            println!("  - {}", finding.suggestion);
+        }
+
+        if check_config.strict {
+            let names: Vec<&str> = failed.iter().map(|finding| finding.name).collect();
+            return Err(CalibrationError::Calibration(format!(
+                "pre-calibration checks failed: {}",
+                names.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// 画像を格子に区切り、コーナーが含まれるセルの割合を求める
+    fn check_coverage(img_points: &Vector<Vector<Point2f>>, frame_size: Size, check_config: &CheckConfig) -> Result<Finding> {
+        let (cols, rows) = check_config.coverage_grid;
+        let occupancy = Self::occupancy(img_points, frame_size, cols, rows);
+        let occupied_cnt = occupancy.iter().flatten().filter(|cnt| **cnt > 0).count();
+        let coverage = occupied_cnt as f64 / (cols * rows) as f64;
+
+        // 空のセルを '.' で示した被覆マップ
+        let map: Vec<String> = occupancy
+            .iter()
+            .map(|row| row.iter().map(|cnt| if *cnt > 0 { '#' } else { '.' }).collect())
+            .collect();
+
+        Ok(Finding {
+            name: "coverage",
+            passed: coverage >= check_config.min_coverage,
+            summary: format!(
+                "{:.0}% of the {}x{} image grid contains corners (min {:.0}%)",
+                coverage * 100.0,
+                cols,
+                rows,
+                check_config.min_coverage * 100.0
+            ),
+            suggestion: format!(
+                "views with the board in the empty cells ('.') of the image grid: {}",
+                map.join(" / ")
+            ),
+        })
+    }
+
+    /// ボードの傾きと距離の範囲を確認する
+    /// 歪みを無視した初期カメラ行列で各ビューの姿勢を推定する
+    fn check_poses(
+        obj_points: &Vector<Vector<Point3f>>,
+        img_points: &Vector<Vector<Point2f>>,
+        frame_size: Size,
+        check_config: &CheckConfig,
+    ) -> Result<Vec<Finding>> {
+        let camera_matrix = calib3d::init_camera_matrix_2d(obj_points, img_points, frame_size, 1.0)?;
+        let dist_coeffs = Mat::default();
+
+        let mut tilts = Vec::new();
+        let mut distances = Vec::new();
+        for (object_points, image_points) in obj_points.iter().zip(img_points.iter()) {
+            let mut rvec = Mat::default();
+            let mut tvec = Mat::default();
+            let solved = calib3d::solve_pnp(
+                &object_points,
+                &image_points,
+                &camera_matrix,
+                &dist_coeffs,
+                &mut rvec,
+                &mut tvec,
+                false,
+                calib3d::SOLVEPNP_ITERATIVE,
+            )?;
+            if !solved {
+                continue;
+            }
+
+            let mut rotation = Mat::default();
+            calib3d::rodrigues(&rvec, &mut rotation, &mut Mat::default())?;
+            // ボードの法線と光軸のなす角
+            let cos_tilt = rotation.at_2d::<f64>(2, 2)?.abs().min(1.0);
+            tilts.push(cos_tilt.acos().to_degrees());
+
+            let t = tvec.data_typed::<f64>()?;
+            distances.push((t[0] * t[0] + t[1] * t[1] + t[2] * t[2]).sqrt());
+        }
+
+        if tilts.is_empty() {
+            return Err(CalibrationError::Calibration("failed to estimate the board pose in any view".to_string()));
+        }
+
+        let (min_tilt, max_tilt) = min_max(&tilts);
+        let (min_distance, max_distance) = min_max(&distances);
+        let distance_ratio = if min_distance > 0.0 { max_distance / min_distance } else { 0.0 };
+
+        Ok(vec![
+            Finding {
+                name: "tilt",
+                passed: max_tilt - min_tilt >= check_config.min_tilt_range_deg,
+                summary: format!(
+                    "board tilt ranges from {:.1} to {:.1} degrees (min range {:.1} degrees)",
+                    min_tilt, max_tilt, check_config.min_tilt_range_deg
+                ),
+                suggestion: format!(
+                    "views with the board tilted more strongly (up to {:.0} degrees or more) around both axes",
+                    min_tilt + check_config.min_tilt_range_deg
+                ),
+            },
+            Finding {
+                name: "distance",
+                passed: distance_ratio >= check_config.min_distance_ratio,
+                summary: format!(
+                    "farthest view is {:.2}x the distance of the nearest (min {:.2}x)",
+                    distance_ratio, check_config.min_distance_ratio
+                ),
+                suggestion: "views with the board closer to the camera (filling the frame) and farther away".to_string(),
+            },
+        ])
+    }
+
+    /// 格子のセルごとのコーナー数 (rows x cols)
+    pub fn occupancy(img_points: &Vector<Vector<Point2f>>, frame_size: Size, cols: i32, rows: i32) -> Vec<Vec<usize>> {
+        let mut occupancy = vec![vec![0; cols as usize]; rows as usize];
+        let cell_width = frame_size.width as f32 / cols as f32;
+        let cell_height = frame_size.height as f32 / rows as f32;
+
+        for point in img_points.iter().flatten() {
+            let col = (point.x / cell_width).floor() as i32;
+            let row = (point.y / cell_height).floor() as i32;
+            if (0..cols).contains(&col) && (0..rows).contains(&row) {
+                occupancy[row as usize][col as usize] += 1;
+            }
+        }
+        occupancy
+    }
+}
+
+fn min_max(values: &[f64]) -> (f64, f64) {
+    values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| (min.min(*v), max.max(*v)))
+}
@@ -0,0 +1,482 @@
+use std::path::{Path, PathBuf};
+
+use opencv::{
+    calib3d,
+    core::{self, Mat, Point, Point2f, Scalar, Size, Vector},
+    imgcodecs,
+    imgproc,
+    prelude::*,
+};
+
+use crate::{camera_calibration::Detection, error::Result, file::CustomFile};
+
+const DETECTED_COLOR: (f64, f64, f64) = (0.0, 255.0, 0.0); // GREEN
+const REPROJECTED_COLOR: (f64, f64, f64) = (0.0, 0.0, 255.0); // RED
+const RESIDUAL_COLOR: (f64, f64, f64) = (0.0, 255.0, 255.0); // YELLOW
+
+/// 歪みの変位を計算する格子の間隔 [px]
+const DISTORTION_GRID_STEP: i32 = 8;
+/// 歪みのベクトル場で横に並べる矢印の数
+const DISTORTION_VECTOR_COLS: i32 = 32;
+/// 放射方向の歪みのグラフ
+const RADIAL_PLOT_WIDTH: i32 = 1000;
+const RADIAL_PLOT_HEIGHT: i32 = 600;
+const RADIAL_SAMPLE_CNT: usize = 200;
+
+pub struct Visualization {}
+
+impl Visualization {
+    /// 全ビューの検出点を画像全体に重ねたヒートマップと散布図を出力する
+    ///
+    /// `background_path` の画像があれば薄く重ね、`grid` (cols, rows) の格子線を描く
+    pub fn coverage_heatmap(
+        img_points: &Vector<Vector<Point2f>>,
+        frame_size: Size,
+        grid: (i32, i32),
+        background_path: Option<&str>,
+        output_path: &str,
+    ) -> Result<()> {
+        // 検出点の密度をぼかしてヒートマップにする
+        let mut density = Mat::new_size_with_default(frame_size, core::CV_32FC1, Scalar::all(0.0))?;
+        let mut point_cnt = 0;
+        for point in img_points.iter().flatten() {
+            let (x, y) = (point.x.round() as i32, point.y.round() as i32);
+            if (0..frame_size.width).contains(&x) && (0..frame_size.height).contains(&y) {
+                *density.at_2d_mut::<f32>(y, x)? += 1.0;
+                point_cnt += 1;
+            }
+        }
+
+        let sigma = frame_size.width.max(frame_size.height) as f64 / 40.0;
+        let mut blurred = Mat::default();
+        imgproc::gaussian_blur_def(&density, &mut blurred, Size::new(0, 0), sigma)?;
+        let mut normalized = Mat::default();
+        core::normalize(&blurred, &mut normalized, 0.0, 255.0, core::NORM_MINMAX, core::CV_8U, &core::no_array())?;
+        let mut heatmap = Mat::default();
+        imgproc::apply_color_map(&normalized, &mut heatmap, imgproc::COLORMAP_JET)?;
+
+        let mut img = match background_image(background_path, frame_size)? {
+            Some(background) => {
+                let mut blended = Mat::default();
+                core::add_weighted(&heatmap, 0.6, &background, 0.4, 0.0, &mut blended, -1)?;
+                blended
+            }
+            None => heatmap,
+        };
+
+        // 格子線 (ViewCheck の被覆チェックと同じ区切り)
+        let (cols, rows) = grid;
+        let grid_color = Scalar::new(255.0, 255.0, 255.0, 0.0);
+        for col in 1..cols {
+            let x = frame_size.width * col / cols;
+            imgproc::line(&mut img, Point::new(x, 0), Point::new(x, frame_size.height), grid_color, 1, imgproc::LINE_AA, 0)?;
+        }
+        for row in 1..rows {
+            let y = frame_size.height * row / rows;
+            imgproc::line(&mut img, Point::new(0, y), Point::new(frame_size.width, y), grid_color, 1, imgproc::LINE_AA, 0)?;
+        }
+
+        // 検出点の散布図
+        let point_radius = (frame_size.width / 400).max(2);
+        for point in img_points.iter().flatten() {
+            let center = Point::new(point.x.round() as i32, point.y.round() as i32);
+            imgproc::circle(&mut img, center, point_radius, Scalar::new(255.0, 255.0, 255.0, 0.0), imgproc::FILLED, imgproc::LINE_AA, 0)?;
+        }
+
+        put_label(&mut img, &format!("{} corners / {} views", point_cnt, img_points.len()))?;
+
+        imgcodecs::imwrite(output_path, &img, &Vector::new())?;
+        println!("Coverage image written to: {}", output_path);
+        Ok(())
+    }
+
+    /// ビューごとの残差画像のファイル名
+    /// 別のディレクトリにある同じ名前の画像が上書きし合わないよう、ビューの番号を前に付ける
+    pub fn residual_image_file_name(view_index: usize, detection: &Detection) -> String {
+        let file_stem = Path::new(&detection.file_name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!("{:03}_{}.jpeg", view_index + 1, file_stem)
+    }
+
+    /// ビューごとの再投影残差の画像と、全ビューの残差をまとめた矢印図を出力する
+    ///
+    /// 各ビューの画像には検出点 (緑)、最終的なキャリブレーションによる再投影点 (赤) と、
+    /// `residual_scale` 倍した残差ベクトル (黄) を描く
+    #[allow(clippy::too_many_arguments)]
+    pub fn residual_images(
+        detections: &[Detection],
+        rvecs: &Vector<Mat>,
+        tvecs: &Vector<Mat>,
+        camera_matrix: &Mat,
+        dist_coeffs: &Mat,
+        frame_size: Size,
+        residual_scale: f64,
+        residual_dir: &str,
+        quiver_path: &str,
+    ) -> Result<()> {
+        CustomFile::create_out_dir(residual_dir)?;
+
+        let mut quiver = Mat::new_size_with_default(frame_size, core::CV_8UC3, Scalar::all(255.0))?;
+        let mut max_residual = 0.0f64;
+        let mut residuals_per_view = Vec::new();
+
+        for (i, detection) in detections.iter().enumerate() {
+            // 姿勢を求められなかったビュー (空の rvec) は描かない
+            let rvec = rvecs.get(i)?;
+            if rvec.empty() {
+                continue;
+            }
+            let object_points = detection.object_points();
+            let image_points = detection.image_points();
+            let mut projected = Vector::<Point2f>::new();
+            calib3d::project_points(
+                &object_points,
+                &rvec,
+                &tvecs.get(i)?,
+                camera_matrix,
+                dist_coeffs,
+                &mut projected,
+                &mut Mat::default(),
+                0.0,
+            )?;
+
+            let pairs: Vec<(Point2f, Point2f)> = image_points.iter().zip(projected.iter()).collect();
+            for (detected, reprojected) in &pairs {
+                let residual = ((reprojected.x - detected.x).powi(2) + (reprojected.y - detected.y).powi(2)).sqrt();
+                max_residual = max_residual.max(residual as f64);
+            }
+
+            let mut img = imgcodecs::imread(&detection.image_path, imgcodecs::IMREAD_COLOR)?;
+            if img.empty() {
+                img = Mat::new_size_with_default(frame_size, core::CV_8UC3, Scalar::all(0.0))?;
+            }
+            draw_residuals(&mut img, &pairs, residual_scale, true)?;
+            put_label(&mut img, &format!("{} (residuals x{})", detection.file_name, residual_scale))?;
+
+            let residual_path = PathBuf::from(residual_dir).join(Self::residual_image_file_name(i, detection));
+            imgcodecs::imwrite(&residual_path.to_string_lossy(), &img, &Vector::new())?;
+
+            residuals_per_view.push(pairs);
+        }
+
+        // 全ビューの残差を1枚にまとめる。系統的なずれ (歪みモデルの不適合) は向きの揃った矢印として現れる
+        for pairs in &residuals_per_view {
+            draw_residuals(&mut quiver, pairs, residual_scale, false)?;
+        }
+        put_label(
+            &mut quiver,
+            &format!("{} views, residuals x{}, max {:.3} px", residuals_per_view.len(), residual_scale, max_residual),
+        )?;
+        imgcodecs::imwrite(quiver_path, &quiver, &Vector::new())?;
+
+        println!("Residual images written to: {}", residual_dir);
+        println!("Residual quiver plot written to: {}", quiver_path);
+        Ok(())
+    }
+
+    /// 歪みモデルを画像として出力する
+    ///
+    /// - `magnitude_path`: 歪みによる変位量 (歪みのある画素位置と歪みのない位置の距離) の等高線図
+    /// - `vectors_path`: 歪みのある位置から歪みのない位置への変位のベクトル場
+    /// - `radial_path`: 主点からの半径に対する放射方向の変位のグラフ
+    ///
+    /// `observed_radius` を与えると、検出点が存在した最大半径をグラフに示す。
+    /// これより外側の曲線はデータに拘束されていない外挿である
+    pub fn distortion_images(
+        camera_matrix: &Mat,
+        dist_coeffs: &Mat,
+        frame_size: Size,
+        observed_radius: Option<f64>,
+        magnitude_path: &str,
+        vectors_path: &str,
+        radial_path: &str,
+    ) -> Result<()> {
+        let displacement = displacement_field(camera_matrix, dist_coeffs, frame_size, DISTORTION_GRID_STEP)?;
+
+        let img = draw_displacement_magnitude(&displacement, frame_size)?;
+        imgcodecs::imwrite(magnitude_path, &img, &Vector::new())?;
+
+        let img = draw_displacement_vectors(&displacement, frame_size)?;
+        imgcodecs::imwrite(vectors_path, &img, &Vector::new())?;
+
+        let img = draw_radial_curve(camera_matrix, dist_coeffs, frame_size, observed_radius)?;
+        imgcodecs::imwrite(radial_path, &img, &Vector::new())?;
+
+        println!("Distortion magnitude written to: {}", magnitude_path);
+        println!("Distortion vector field written to: {}", vectors_path);
+        println!("Radial distortion curve written to: {}", radial_path);
+        Ok(())
+    }
+}
+
+/// `step` 画素ごとの格子点で、歪みのある位置から歪みのない位置への変位 (dx, dy) を求める
+fn displacement_field(camera_matrix: &Mat, dist_coeffs: &Mat, frame_size: Size, step: i32) -> opencv::Result<Vec<Vec<(f64, f64)>>> {
+    let cols = frame_size.width / step + 1;
+    let rows = frame_size.height / step + 1;
+
+    let mut distorted = Vector::<Point2f>::new();
+    for row in 0..rows {
+        for col in 0..cols {
+            distorted.push(Point2f::new((col * step) as f32, (row * step) as f32));
+        }
+    }
+
+    // P にカメラ行列を渡して、歪みのない位置を画素座標で得る
+    let mut undistorted = Vector::<Point2f>::new();
+    calib3d::undistort_points(&distorted, &mut undistorted, camera_matrix, dist_coeffs, &core::no_array(), camera_matrix)?;
+
+    let mut field = vec![Vec::with_capacity(cols as usize); rows as usize];
+    for (i, (d, u)) in distorted.iter().zip(undistorted.iter()).enumerate() {
+        field[i / cols as usize].push(((u.x - d.x) as f64, (u.y - d.y) as f64));
+    }
+    Ok(field)
+}
+
+/// 変位量をカラーマップで塗り、一定間隔の等高線とその値を重ねる
+fn draw_displacement_magnitude(displacement: &[Vec<(f64, f64)>], frame_size: Size) -> opencv::Result<Mat> {
+    let magnitude: Vec<Vec<f32>> = displacement
+        .iter()
+        .map(|row| row.iter().map(|(dx, dy)| dx.hypot(*dy) as f32).collect())
+        .collect();
+    let max_displacement = magnitude.iter().flatten().fold(0.0f32, |max, v| max.max(*v)) as f64;
+    let magnitude = Mat::from_slice_2d(&magnitude)?;
+
+    // 格子の端点が画像の端に来るように拡大する
+    let mut resized = Mat::default();
+    imgproc::resize(&magnitude, &mut resized, frame_size, 0.0, 0.0, imgproc::INTER_LINEAR)?;
+    let mut normalized = Mat::default();
+    core::normalize(&resized, &mut normalized, 0.0, 255.0, core::NORM_MINMAX, core::CV_8U, &core::no_array())?;
+    let mut img = Mat::default();
+    imgproc::apply_color_map(&normalized, &mut img, imgproc::COLORMAP_JET)?;
+
+    let level_step = contour_step(max_displacement);
+    let scale = frame_size.width as f64 / 1000.0;
+    let mut level = level_step;
+    while level < max_displacement {
+        let mut mask = Mat::default();
+        imgproc::threshold(&resized, &mut mask, level, 255.0, imgproc::THRESH_BINARY)?;
+        let mut mask_8u = Mat::default();
+        mask.convert_to(&mut mask_8u, core::CV_8U, 1.0, 0.0)?;
+
+        let mut contours = Vector::<Vector<Point>>::new();
+        imgproc::find_contours_def(&mask_8u, &mut contours, imgproc::RETR_LIST, imgproc::CHAIN_APPROX_SIMPLE)?;
+        imgproc::draw_contours(
+            &mut img,
+            &contours,
+            -1,
+            Scalar::all(255.0),
+            1,
+            imgproc::LINE_AA,
+            &core::no_array(),
+            i32::MAX,
+            Point::new(0, 0),
+        )?;
+
+        // 等高線の中ほどに値を書く
+        for contour in contours.iter().filter(|contour| contour.len() > 10) {
+            let org = contour.get(contour.len() / 2)?;
+            let text = format_level(level);
+            imgproc::put_text(&mut img, &text, org, imgproc::FONT_HERSHEY_SIMPLEX, 0.5 * scale, Scalar::all(255.0), 1, imgproc::LINE_AA, false)?;
+        }
+        level += level_step;
+    }
+
+    put_label(
+        &mut img,
+        &format!("distortion displacement, max {:.1} px, contours every {} px", max_displacement, format_level(level_step)),
+    )?;
+    Ok(img)
+}
+
+/// 変位を矢印で描く。最大の変位が格子の間隔程度の長さになるように拡大する
+fn draw_displacement_vectors(displacement: &[Vec<(f64, f64)>], frame_size: Size) -> opencv::Result<Mat> {
+    let mut img = Mat::new_size_with_default(frame_size, core::CV_8UC3, Scalar::all(255.0))?;
+
+    // 矢印を描く格子の間隔 (変位を計算した格子の何点おきか)
+    let stride = (frame_size.width / DISTORTION_GRID_STEP / DISTORTION_VECTOR_COLS).max(1) as usize;
+    let spacing = (stride as i32 * DISTORTION_GRID_STEP) as f64;
+    let max_displacement = displacement
+        .iter()
+        .flatten()
+        .map(|(dx, dy)| dx.hypot(*dy))
+        .fold(0.0f64, f64::max);
+    let vector_scale = if max_displacement > 0.0 { 0.9 * spacing / max_displacement } else { 1.0 };
+
+    let mut pairs = Vec::new();
+    for (row, values) in displacement.iter().enumerate().step_by(stride) {
+        for (col, (dx, dy)) in values.iter().enumerate().step_by(stride) {
+            let x = (col as i32 * DISTORTION_GRID_STEP) as f32;
+            let y = (row as i32 * DISTORTION_GRID_STEP) as f32;
+            pairs.push((Point2f::new(x, y), Point2f::new(x + *dx as f32, y + *dy as f32)));
+        }
+    }
+    draw_residuals(&mut img, &pairs, vector_scale, false)?;
+
+    put_label(
+        &mut img,
+        &format!("distortion vectors x{:.1}, max {:.1} px", vector_scale, max_displacement),
+    )?;
+    Ok(img)
+}
+
+/// 主点からの半径 [px] に対する放射方向の変位 [px] のグラフを描く
+///
+/// 接線方向の歪み (p1, p2) と薄プリズム歪みは含めない
+fn draw_radial_curve(camera_matrix: &Mat, dist_coeffs: &Mat, frame_size: Size, observed_radius: Option<f64>) -> opencv::Result<Mat> {
+    let focal_length = *camera_matrix.at_2d::<f64>(0, 0)?;
+    let cx = *camera_matrix.at_2d::<f64>(0, 2)?;
+    let cy = *camera_matrix.at_2d::<f64>(1, 2)?;
+    // 歪みなしのモデル (Distortion::None) は空の Mat になるため、係数をすべて 0 とする
+    let coeffs: &[f64] = if dist_coeffs.empty() { &[] } else { dist_coeffs.data_typed::<f64>()? };
+    let k = |i: usize| coeffs.get(i).copied().unwrap_or(0.0);
+    let (k1, k2, k3, k4, k5, k6) = (k(0), k(1), k(4), k(5), k(6), k(7));
+
+    // 主点から最も遠い画像の隅までをグラフにする
+    let max_radius = [(0.0, 0.0), (frame_size.width as f64, 0.0), (0.0, frame_size.height as f64), (frame_size.width as f64, frame_size.height as f64)]
+        .iter()
+        .map(|(x, y)| (x - cx).hypot(y - cy))
+        .fold(0.0f64, f64::max);
+
+    let samples: Vec<(f64, f64)> = (0..=RADIAL_SAMPLE_CNT)
+        .map(|i| {
+            let radius = max_radius * i as f64 / RADIAL_SAMPLE_CNT as f64;
+            let r = radius / focal_length;
+            let r2 = r * r;
+            let factor = (1.0 + k1 * r2 + k2 * r2 * r2 + k3 * r2 * r2 * r2) / (1.0 + k4 * r2 + k5 * r2 * r2 + k6 * r2 * r2 * r2);
+            (radius, radius * (factor - 1.0))
+        })
+        .collect();
+
+    let (min_value, max_value) = samples
+        .iter()
+        .fold((0.0f64, 0.0f64), |(min, max), (_, v)| (min.min(*v), max.max(*v)));
+    let value_range = (max_value - min_value).max(1e-6);
+
+    let plot_size = Size::new(RADIAL_PLOT_WIDTH, RADIAL_PLOT_HEIGHT);
+    let mut img = Mat::new_size_with_default(plot_size, core::CV_8UC3, Scalar::all(255.0))?;
+    let (left, right, top, bottom) = (80, RADIAL_PLOT_WIDTH - 30, 60, RADIAL_PLOT_HEIGHT - 60);
+    let to_point = |radius: f64, value: f64| {
+        Point::new(
+            left + ((right - left) as f64 * radius / max_radius.max(1.0)).round() as i32,
+            bottom - ((bottom - top) as f64 * (value - min_value) / value_range).round() as i32,
+        )
+    };
+
+    let axis_color = Scalar::all(0.0);
+    let grid_color = Scalar::all(200.0);
+    let font = imgproc::FONT_HERSHEY_SIMPLEX;
+
+    // 変位 0 の線と軸
+    imgproc::line(&mut img, to_point(0.0, 0.0), to_point(max_radius, 0.0), grid_color, 1, imgproc::LINE_AA, 0)?;
+    imgproc::line(&mut img, Point::new(left, top), Point::new(left, bottom), axis_color, 1, imgproc::LINE_AA, 0)?;
+    imgproc::line(&mut img, Point::new(left, bottom), Point::new(right, bottom), axis_color, 1, imgproc::LINE_AA, 0)?;
+    for (text, value) in [(format!("{:.1}", max_value), max_value), (format!("{:.1}", min_value), min_value), ("0".to_string(), 0.0)] {
+        let org = Point::new(5, to_point(0.0, value).y + 5);
+        imgproc::put_text(&mut img, &text, org, font, 0.45, axis_color, 1, imgproc::LINE_AA, false)?;
+    }
+    imgproc::put_text(&mut img, &format!("{:.0}", max_radius), Point::new(right - 40, bottom + 20), font, 0.45, axis_color, 1, imgproc::LINE_AA, false)?;
+    imgproc::put_text(&mut img, "radius from principal point [px]", Point::new(left, bottom + 45), font, 0.5, axis_color, 1, imgproc::LINE_AA, false)?;
+
+    // 画像の短辺の端 (これより外側は画像の隅のみ)
+    let edge_radius = [cx, frame_size.width as f64 - cx, cy, frame_size.height as f64 - cy]
+        .iter()
+        .fold(f64::INFINITY, |min, v| min.min(*v));
+    let edge_x = to_point(edge_radius, 0.0).x;
+    imgproc::line(&mut img, Point::new(edge_x, top), Point::new(edge_x, bottom), grid_color, 1, imgproc::LINE_AA, 0)?;
+    imgproc::put_text(&mut img, "image edge", Point::new(edge_x + 5, top + 15), font, 0.45, Scalar::all(120.0), 1, imgproc::LINE_AA, false)?;
+
+    if let Some(observed_radius) = observed_radius {
+        let x = to_point(observed_radius.min(max_radius), 0.0).x;
+        let color = Scalar::new(0.0, 0.0, 255.0, 0.0);
+        imgproc::line(&mut img, Point::new(x, top), Point::new(x, bottom), color, 1, imgproc::LINE_AA, 0)?;
+        imgproc::put_text(&mut img, "outermost corner", Point::new(x + 5, top + 35), font, 0.45, color, 1, imgproc::LINE_AA, false)?;
+    }
+
+    let curve: Vector<Point> = samples.iter().map(|(radius, value)| to_point(*radius, *value)).collect();
+    let mut curves = Vector::<Vector<Point>>::new();
+    curves.push(curve);
+    imgproc::polylines(&mut img, &curves, false, Scalar::new(128.0, 0.0, 0.0, 0.0), 2, imgproc::LINE_AA, 0)?;
+
+    let title = format!(
+        "radial displacement [px]  k1={:.4} k2={:.4} k3={:.4} k4={:.4} k5={:.4} k6={:.4}",
+        k1, k2, k3, k4, k5, k6
+    );
+    imgproc::put_text(&mut img, &title, Point::new(10, 30), font, 0.5, axis_color, 1, imgproc::LINE_AA, false)?;
+    Ok(img)
+}
+
+/// 等高線がおよそ8本になるように 1, 2, 5 × 10^n の間隔を選ぶ
+fn contour_step(max_value: f64) -> f64 {
+    if max_value <= 0.0 {
+        return 1.0;
+    }
+    let raw = max_value / 8.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+fn format_level(level: f64) -> String {
+    if level >= 1.0 {
+        format!("{:.0}", level)
+    } else {
+        format!("{}", (level * 1000.0).round() / 1000.0)
+    }
+}
+
+/// 検出点から再投影点へ向かう残差ベクトルを拡大して描く
+fn draw_residuals(img: &mut Mat, pairs: &[(Point2f, Point2f)], residual_scale: f64, draw_points: bool) -> opencv::Result<()> {
+    // 小数点以下4ビットの精度で描画する
+    const SHIFT: i32 = 4;
+    let to_subpx = |x: f64| (x * (1 << SHIFT) as f64).round() as i32;
+    let color = |(b, g, r): (f64, f64, f64)| Scalar::new(b, g, r, 0.0);
+    let radius = (img.cols() / 300).max(2);
+
+    for (detected, reprojected) in pairs {
+        let start = Point::new(to_subpx(detected.x as f64), to_subpx(detected.y as f64));
+        let end = Point::new(
+            to_subpx(detected.x as f64 + (reprojected.x - detected.x) as f64 * residual_scale),
+            to_subpx(detected.y as f64 + (reprojected.y - detected.y) as f64 * residual_scale),
+        );
+
+        if draw_points {
+            let reprojected_point = Point::new(to_subpx(reprojected.x as f64), to_subpx(reprojected.y as f64));
+            imgproc::circle(img, start, radius << SHIFT, color(DETECTED_COLOR), 1, imgproc::LINE_AA, SHIFT)?;
+            imgproc::circle(img, reprojected_point, (radius << SHIFT) / 2, color(REPROJECTED_COLOR), imgproc::FILLED, imgproc::LINE_AA, SHIFT)?;
+            imgproc::arrowed_line(img, start, end, color(RESIDUAL_COLOR), 2, imgproc::LINE_AA, SHIFT, 0.2)?;
+        } else {
+            imgproc::arrowed_line(img, start, end, Scalar::new(128.0, 0.0, 0.0, 0.0), 1, imgproc::LINE_AA, SHIFT, 0.2)?;
+        }
+    }
+    Ok(())
+}
+
+/// 背景画像をグレースケールで読み込み、フレームサイズに合わせる
+fn background_image(background_path: Option<&str>, frame_size: Size) -> opencv::Result<Option<Mat>> {
+    let Some(path) = background_path else {
+        return Ok(None);
+    };
+    let gray = imgcodecs::imread(path, imgcodecs::IMREAD_GRAYSCALE)?;
+    if gray.empty() {
+        return Ok(None);
+    }
+
+    let mut resized = Mat::default();
+    imgproc::resize(&gray, &mut resized, frame_size, 0.0, 0.0, imgproc::INTER_AREA)?;
+    let mut background = Mat::default();
+    imgproc::cvt_color_def(&resized, &mut background, imgproc::COLOR_GRAY2BGR)?;
+    Ok(Some(background))
+}
+
+/// 画像の左上に説明を描く
+fn put_label(img: &mut Mat, text: &str) -> opencv::Result<()> {
+    let scale = img.cols() as f64 / 1000.0;
+    let org = Point::new(10, (40.0 * scale).max(20.0) as i32);
+    imgproc::put_text(img, text, org, imgproc::FONT_HERSHEY_SIMPLEX, scale, Scalar::all(0.0), 6, imgproc::LINE_AA, false)?;
+    imgproc::put_text(img, text, org, imgproc::FONT_HERSHEY_SIMPLEX, scale, Scalar::all(255.0), 2, imgproc::LINE_AA, false)
+}